@@ -0,0 +1,159 @@
+use bevy_ecs::prelude::*;
+use rust_i18n::t;
+use serde::Deserialize;
+use std::{error::Error, time::Duration};
+
+use crate::{
+    clock::GameClock,
+    components::{Achievements, BoughtUpgrades, EventMessage},
+    game::Config,
+    save,
+};
+
+/// The `[achievements]` section: long-term goals kept across runs.
+#[derive(Debug, Deserialize)]
+pub struct AchievementsConfig {
+    /// File the unlocked achievements are kept in.
+    pub path: String,
+    pub table: Vec<AchievementConfig>,
+}
+
+/// An achievement; its name and description live under
+/// `game.achievements.list.<id>` in the locales.
+#[derive(Debug, Deserialize)]
+pub struct AchievementConfig {
+    pub id: String,
+    pub icon: String,
+    pub condition: Condition,
+}
+
+impl AchievementConfig {
+    pub fn name(&self) -> String {
+        t!(format!("game.achievements.list.{}.name", self.id)).to_string()
+    }
+
+    pub fn description(&self) -> String {
+        t!(format!("game.achievements.list.{}.description", self.id)).to_string()
+    }
+}
+
+/// What a run has to reach for an achievement to unlock.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    /// Gold earned over a single run.
+    GoldEarned { gold_earned: u64 },
+    /// Units of one upgrade owned at once.
+    Upgrade { upgrade: String, count: u32 },
+    /// Units of all upgrades owned at once.
+    Units { units: u32 },
+    /// Events the castle came through in a single run.
+    EventsSurvived { events_survived: u32 },
+    /// Seconds a single castle has stood.
+    Survived { survived: f32 },
+    /// An upgrade bought up to its max level.
+    Maxed { maxed: String },
+}
+
+/// The run as far as achievements are concerned.
+pub struct RunProgress<'a> {
+    pub gold_earned: u64,
+    pub bought_upgrades: &'a BoughtUpgrades,
+    pub events_survived: u32,
+    pub run_time: Duration,
+}
+
+impl Condition {
+    pub fn is_met(&self, progress: &RunProgress, config: &Config) -> bool {
+        match self {
+            Condition::GoldEarned { gold_earned } => progress.gold_earned >= *gold_earned,
+            Condition::Upgrade { upgrade, count } => {
+                progress.bought_upgrades.get_count(upgrade) >= *count
+            }
+            Condition::Units { units } => {
+                progress.bought_upgrades.0.values().sum::<u32>() >= *units
+            }
+            Condition::EventsSurvived { events_survived } => {
+                progress.events_survived >= *events_survived
+            }
+            Condition::Survived { survived } => progress.run_time.as_secs_f32() >= *survived,
+            Condition::Maxed { maxed } => config
+                .upgrade(maxed)
+                .is_some_and(|upgrade| upgrade.is_maxed(progress.bought_upgrades.get_count(maxed))),
+        }
+    }
+}
+
+/// Achievements whose condition `progress` meets and that are still locked.
+pub fn newly_unlocked<'a>(
+    achievements: &Achievements,
+    progress: &RunProgress,
+    config: &'a Config,
+) -> Vec<&'a AchievementConfig> {
+    config
+        .achievements
+        .table
+        .iter()
+        .filter(|achievement| !achievements.unlocked.contains_key(&achievement.id))
+        .filter(|achievement| achievement.condition.is_met(progress, config))
+        .collect()
+}
+
+/// Writes the achievements file whenever something new was unlocked.
+pub fn persist_system(
+    mut query: Query<(&mut Achievements, &mut EventMessage, &Config)>,
+    clock: Res<GameClock>,
+) {
+    for (mut achievements, mut event_message, config) in query.iter_mut() {
+        if !achievements.unsaved {
+            continue;
+        }
+        // Retried with the next unlock rather than every frame
+        achievements.unsaved = false;
+        if save::write_toml(&config.achievements.path, &*achievements).is_err() {
+            event_message.message = t!("game.achievements.save_failed").to_string();
+            event_message.timestamp = clock.now();
+        }
+    }
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn Error>> {
+    let table = &config.achievements.table;
+    for (index, achievement) in table.iter().enumerate() {
+        if table[..index]
+            .iter()
+            .any(|other| other.id == achievement.id)
+        {
+            return Err(format!("duplicate achievement id {}", achievement.id).into());
+        }
+        let upgrade = match &achievement.condition {
+            Condition::Upgrade { upgrade, .. } | Condition::Maxed { maxed: upgrade } => upgrade,
+            _ => continue,
+        };
+        if config.upgrade(upgrade).is_none() {
+            return Err(format!(
+                "achievement {} refers to unknown upgrade {upgrade}",
+                achievement.id
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_achievement_has_a_name_and_description() {
+        rust_i18n::set_locale("ru");
+        // The shipped table, since that is the one the locales have to cover
+        let config = Config::load().expect("Failed to load game config");
+        for achievement in &config.achievements.table {
+            // A missing translation comes back as its own key
+            assert!(!achievement.name().contains(&achievement.id));
+            assert!(!achievement.description().contains(&achievement.id));
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use bevy_ecs::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Source of "now" for every system, so runs can be tested and replayed.
+#[derive(Resource, Debug)]
+pub enum GameClock {
+    /// Follows the monotonic system clock.
+    Real,
+    /// Stands still until advanced explicitly, e.g. by tests or a simulation.
+    Manual(Instant),
+}
+
+impl GameClock {
+    pub fn manual() -> Self {
+        Self::Manual(Instant::now())
+    }
+
+    pub fn now(&self) -> Instant {
+        match self {
+            Self::Real => Instant::now(),
+            Self::Manual(now) => *now,
+        }
+    }
+
+    /// Fast-forwards a manual clock; the real clock cannot be moved.
+    pub fn advance(&mut self, by: Duration) {
+        if let Self::Manual(now) = self {
+            *now += by;
+        }
+    }
+}
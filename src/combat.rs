@@ -0,0 +1,199 @@
+use serde::Deserialize;
+use std::ops::{Add, Mul};
+
+use crate::{components::BoughtUpgrades, game::Config};
+
+/// The `[combat]` section: how the army softens event damage.
+#[derive(Debug, Deserialize)]
+pub struct CombatConfig {
+    /// Share of damage prevented per point of matching strength.
+    pub mitigation_per_point: f32,
+    /// Damage share the army can prevent at most.
+    pub max_mitigation: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CombatKind {
+    Melee,
+    Ranged,
+    Siege,
+}
+
+/// Strength of a unit, or of the whole army, by kind of fighting.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct CombatStats {
+    pub melee: u64,
+    pub ranged: u64,
+    pub siege: u64,
+}
+
+impl CombatStats {
+    pub fn get(&self, kind: CombatKind) -> u64 {
+        match kind {
+            CombatKind::Melee => self.melee,
+            CombatKind::Ranged => self.ranged,
+            CombatKind::Siege => self.siege,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.melee + self.ranged + self.siege
+    }
+}
+
+impl Add for CombatStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            melee: self.melee + other.melee,
+            ranged: self.ranged + other.ranged,
+            siege: self.siege + other.siege,
+        }
+    }
+}
+
+impl Mul<u32> for CombatStats {
+    type Output = Self;
+
+    fn mul(self, count: u32) -> Self {
+        let count = count as u64;
+        Self {
+            melee: self.melee * count,
+            ranged: self.ranged * count,
+            siege: self.siege * count,
+        }
+    }
+}
+
+/// A unit that is especially good against one event.
+#[derive(Debug, Deserialize)]
+pub struct Counter {
+    pub event: String,
+    /// Share of that event's damage prevented per owned unit.
+    pub mitigation: f32,
+}
+
+/// Combined strength of every owned unit.
+pub fn army_strength(bought_upgrades: &BoughtUpgrades, config: &Config) -> CombatStats {
+    config
+        .upgrades
+        .iter()
+        .map(|upgrade| upgrade.combat * bought_upgrades.get_count(&upgrade.id))
+        .fold(CombatStats::default(), Add::add)
+}
+
+/// Share of an event's defense damage the owned units prevent: strength of the
+/// kind the event attacks with, plus the counters against that very event.
+pub fn mitigation(
+    event_id: &str,
+    kind: Option<CombatKind>,
+    bought_upgrades: &BoughtUpgrades,
+    config: &Config,
+) -> f32 {
+    let strength = kind.map_or(0, |kind| army_strength(bought_upgrades, config).get(kind));
+    let countered: f32 = config
+        .upgrades
+        .iter()
+        .flat_map(|upgrade| {
+            let count = bought_upgrades.get_count(&upgrade.id) as f32;
+            upgrade
+                .counters
+                .iter()
+                .filter(|counter| counter.event == event_id)
+                .map(move |counter| counter.mitigation * count)
+        })
+        .sum();
+
+    (strength as f32 * config.combat.mitigation_per_point + countered)
+        .min(config.combat.max_mitigation)
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if !(0.0..=1.0).contains(&config.combat.max_mitigation) {
+        return Err("combat.max_mitigation must be between 0 and 1".into());
+    }
+
+    for upgrade in &config.upgrades {
+        for counter in &upgrade.counters {
+            if !config
+                .events
+                .table
+                .iter()
+                .any(|event| event.id == counter.event)
+            {
+                return Err(format!(
+                    "upgrade {} counters unknown event {}",
+                    upgrade.id, counter.event
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn army(units: &[(&str, u32)]) -> BoughtUpgrades {
+        let mut bought_upgrades = BoughtUpgrades::default();
+        for (upgrade, count) in units {
+            for _ in 0..*count {
+                bought_upgrades.increment(upgrade);
+            }
+        }
+        bought_upgrades
+    }
+
+    fn counter(upgrade: &str, event: &str, config: &Config) -> f32 {
+        let upgrade = config.upgrade(upgrade).unwrap();
+        let counter = upgrade
+            .counters
+            .iter()
+            .find(|counter| counter.event == event);
+        counter.unwrap().mitigation
+    }
+
+    #[test]
+    fn strength_of_the_event_kind_softens_it() {
+        let config = fixture::config();
+        let warriors = army(&[("warrior", 3)]);
+        let melee = army_strength(&warriors, &config).melee;
+        assert!(melee > 0);
+
+        let softened = mitigation("raid", Some(CombatKind::Melee), &warriors, &config);
+
+        assert_eq!(softened, melee as f32 * config.combat.mitigation_per_point);
+        // An event without a kind is only softened by counters
+        assert_eq!(mitigation("raid", None, &warriors, &config), 0.0);
+    }
+
+    #[test]
+    fn counters_soften_only_their_own_event() {
+        let config = fixture::config();
+        let archers = army(&[("archer", 2)]);
+
+        let raid = mitigation("raid", None, &archers, &config);
+        let siege = mitigation("siege", None, &archers, &config);
+
+        assert_eq!(raid, 2.0 * counter("archer", "raid", &config));
+        assert_eq!(siege, 0.0);
+    }
+
+    #[test]
+    fn mitigation_stops_at_max_mitigation() {
+        let config = fixture::config();
+        let archers = army(&[("archer", 100)]);
+
+        let softened = mitigation("raid", Some(CombatKind::Melee), &archers, &config);
+
+        assert!(100.0 * counter("archer", "raid", &config) > config.combat.max_mitigation);
+        assert_eq!(softened, config.combat.max_mitigation);
+    }
+}
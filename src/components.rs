@@ -1,7 +1,15 @@
 use bevy_ecs::prelude::*;
-use std::{collections::HashMap, ops::AddAssign, time::Instant};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::AddAssign,
+    time::Instant,
+};
 
-use crate::game::Config;
+use crate::{
+    game::{Config, OfflineProgress},
+    save::WallTime,
+};
 
 #[derive(Component)]
 pub struct Gold(pub u64);
@@ -9,6 +17,10 @@ pub struct Gold(pub u64);
 #[derive(Component)]
 pub struct GoldPerSecond(pub u64);
 
+/// All gold that came in this run, including what has been spent since.
+#[derive(Component, Default)]
+pub struct GoldEarned(pub u64);
+
 #[derive(Component)]
 pub struct Defense(pub u64);
 
@@ -18,62 +30,105 @@ pub struct DefensePerSecond(pub u64);
 #[derive(Component)]
 pub struct MaxDefense(pub u64);
 
-#[derive(Component, PartialEq, Copy, Clone, Eq, Hash)]
-pub enum AvailableUpgrades {
-    Catapult,
-    Archer,
-    Warrior,
-    Officer,
-    OilReserve,
-    TradeHall,
-    MidasHand,
+/// How many units a single purchase buys.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BuyQuantity {
+    #[default]
+    One,
+    Ten,
+    Hundred,
+    /// As many as the gold allows.
+    Max,
 }
 
-impl AvailableUpgrades {
-    pub fn cost(&self, config: &Config, bought_upgrades: &BoughtUpgrades) -> u64 {
+impl BuyQuantity {
+    pub fn next(self) -> Self {
+        match self {
+            BuyQuantity::One => BuyQuantity::Ten,
+            BuyQuantity::Ten => BuyQuantity::Hundred,
+            BuyQuantity::Hundred => BuyQuantity::Max,
+            BuyQuantity::Max => BuyQuantity::One,
+        }
+    }
+
+    /// Fixed batch size, `None` for [`BuyQuantity::Max`].
+    pub fn count(self) -> Option<u32> {
         match self {
-            AvailableUpgrades::Catapult => config.upgrades.catapult.cost,
-            AvailableUpgrades::Archer => config.upgrades.archer.cost,
-            AvailableUpgrades::Warrior => config.upgrades.warrior.cost,
-            AvailableUpgrades::Officer => config.upgrades.officer.cost,
-            AvailableUpgrades::OilReserve => config.upgrades.oil.cost,
-            AvailableUpgrades::TradeHall => {
-                let level = bought_upgrades.get_count(self);
-                if level >= config.trade_hall.levels.len() as u32 {
-                    return 0;
-                }
-
-                let level_index = level as usize;
-                (config.trade_hall.base_cost as f32
-                    * config.trade_hall.levels[level_index].cost_multiplier) as u64
-            }
-            AvailableUpgrades::MidasHand => {
-                let level = bought_upgrades.get_count(self);
-                if level >= config.midas_hand.levels.len() as u32 {
-                    return 0;
-                }
-
-                let level_index = level as usize;
-                (config.midas_hand.base_cost as f32
-                    * config.midas_hand.levels[level_index].cost_multiplier) as u64
-            }
+            BuyQuantity::One => Some(1),
+            BuyQuantity::Ten => Some(10),
+            BuyQuantity::Hundred => Some(100),
+            BuyQuantity::Max => None,
         }
     }
 }
 
+/// Id of the currently highlighted upgrade in the shop.
 #[derive(Component)]
-pub struct SelectedUpgrade(pub AvailableUpgrades);
+pub struct SelectedUpgrade(pub String);
 
+/// Owned amount (or level) of every upgrade, keyed by upgrade id.
 #[derive(Component, Default)]
-pub struct BoughtUpgrades(pub HashMap<AvailableUpgrades, u32>);
+pub struct BoughtUpgrades(pub HashMap<String, u32>);
 
 impl BoughtUpgrades {
-    pub fn get_count(&self, upgrade: &AvailableUpgrades) -> u32 {
+    pub fn get_count(&self, upgrade: &str) -> u32 {
         *self.0.get(upgrade).unwrap_or(&0)
     }
 
-    pub fn increment(&mut self, upgrade: &AvailableUpgrades) {
-        self.0.entry(*upgrade).or_insert(0).add_assign(1);
+    pub fn increment(&mut self, upgrade: &str) {
+        self.0.entry(upgrade.to_string()).or_insert(0).add_assign(1);
+    }
+
+    /// Removes up to `count` units, returning how many were actually owned.
+    pub fn remove(&mut self, upgrade: &str, count: u32) -> u32 {
+        let Some(owned) = self.0.get_mut(upgrade) else {
+            return 0;
+        };
+        let removed = count.min(*owned);
+        *owned -= removed;
+        removed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModifierTarget {
+    GoldPerSecond,
+    DefensePerSecond,
+}
+
+impl ModifierTarget {
+    pub fn i18n_key(&self) -> &'static str {
+        match self {
+            ModifierTarget::GoldPerSecond => "game.modifiers.gold_per_second",
+            ModifierTarget::DefensePerSecond => "game.modifiers.defense_per_second",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Modifier {
+    pub target: ModifierTarget,
+    pub factor: f32,
+    pub expires_at: Instant,
+}
+
+/// Temporary rate multipliers applied by events.
+#[derive(Component, Debug, Default)]
+pub struct ActiveModifiers(pub Vec<Modifier>);
+
+impl ActiveModifiers {
+    /// Combined factor of every modifier on `target` that is still active.
+    pub fn factor(&self, target: ModifierTarget, now: Instant) -> f32 {
+        self.0
+            .iter()
+            .filter(|modifier| modifier.target == target && modifier.expires_at > now)
+            .map(|modifier| modifier.factor)
+            .product()
+    }
+
+    pub fn prune(&mut self, now: Instant) {
+        self.0.retain(|modifier| modifier.expires_at > now);
     }
 }
 
@@ -86,53 +141,235 @@ pub struct LastEventCheck(pub Instant);
 #[derive(Component)]
 pub struct LastClick(pub Instant);
 
+#[derive(Component)]
+pub struct LastSave(pub Instant);
+
+#[derive(Component)]
+pub struct RunStarted(pub Instant);
+
+/// The next siege wave and when it arrives.
+#[derive(Component)]
+pub struct Wave {
+    pub number: u32,
+    pub next_at: Instant,
+}
+
+/// A paid repair of the walls, mended a few points every second.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RepairJob {
+    pub points: u64,
+    pub done: u64,
+}
+
+/// Repairs paid for and not finished yet, worked off front to back.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RepairQueue(pub VecDeque<RepairJob>);
+
+impl RepairQueue {
+    /// Points still to be repaired across all jobs.
+    pub fn pending(&self) -> u64 {
+        self.0.iter().map(|job| job.points - job.done).sum()
+    }
+}
+
 #[derive(Component)]
 pub struct EventMessage {
     pub message: String,
     pub timestamp: Instant,
 }
 
+/// Whether the event odds overlay is shown.
+#[derive(Component, Default)]
+pub struct ShowEventOdds(pub bool);
+
 #[derive(Component)]
 pub struct GameRunning(pub bool);
 
-#[derive(Component, PartialEq, Eq, Clone, Copy)]
+/// Summary of the progress credited while the game was closed, shown until dismissed.
+#[derive(Component, Default)]
+pub struct OfflineReport(pub Option<OfflineProgress>);
+
+#[derive(Component, PartialEq, Eq, Clone, Copy, Default)]
 pub enum GameState {
+    #[default]
     Playing,
+    /// The prestige screen; the castle keeps going behind it.
+    Prestige,
+    /// The achievements screen, also shown over a running castle.
+    Achievements,
+    /// The statistics screen, also shown over a running castle.
+    Statistics,
+    /// The leaderboard, also shown over a running castle.
+    Leaderboard,
     GameOver,
     Exiting,
 }
 
-impl Default for GameState {
-    fn default() -> Self {
-        Self::Playing
+impl GameState {
+    /// Whether a run is still going, whichever screen is shown.
+    pub fn in_run(self) -> bool {
+        matches!(
+            self,
+            GameState::Playing
+                | GameState::Prestige
+                | GameState::Achievements
+                | GameState::Statistics
+                | GameState::Leaderboard
+        )
     }
 }
 
-#[derive(Component)]
-pub struct Upgrades(pub [AvailableUpgrades; 7]);
-
-impl Default for Upgrades {
-    fn default() -> Self {
-        Self([
-            AvailableUpgrades::Catapult,
-            AvailableUpgrades::Archer,
-            AvailableUpgrades::Warrior,
-            AvailableUpgrades::Officer,
-            AvailableUpgrades::OilReserve,
-            AvailableUpgrades::TradeHall,
-            AvailableUpgrades::MidasHand,
-        ])
+/// Renown and perks kept from one castle to the next.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Prestige {
+    pub renown: u64,
+    pub castles_abandoned: u32,
+    /// Perk levels by perk id.
+    pub perks: HashMap<String, u32>,
+}
+
+impl Prestige {
+    pub fn level(&self, perk: &str) -> u32 {
+        self.perks.get(perk).copied().unwrap_or(0)
+    }
+}
+
+/// Index of the highlighted perk on the prestige screen.
+#[derive(Component, Default)]
+pub struct SelectedPerk(pub usize);
+
+/// Set while the prestige screen waits for abandoning the castle to be confirmed.
+#[derive(Component, Default)]
+pub struct ConfirmAbandon(pub bool);
+
+/// Running totals of one castle, or of every castle ever played.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Totals {
+    pub clicks: u64,
+    /// Units and levels bought, whether or not they were kept.
+    pub upgrades_bought: u64,
+    pub gold_from_clicks: u64,
+    /// Gold per second paid by upgrades such as the Trade Hall.
+    pub gold_from_income: u64,
+    /// Gold brought by events such as trade caravans.
+    pub gold_from_events: u64,
+    pub gold_from_waves: u64,
+    /// Defense lost by event id.
+    pub defense_lost: HashMap<String, u64>,
+    pub defense_lost_to_waves: u64,
+    pub events_survived: u32,
+    pub waves_repelled: u32,
+    pub peak_defense: u64,
+    /// Seconds the game was open and running.
+    pub seconds_played: f64,
+    pub castles_fallen: u32,
+}
+
+/// Totals of the current run, and of all runs, kept across them in their own file.
+#[derive(Component, Debug, Default)]
+pub struct Statistics {
+    pub run: Totals,
+    pub lifetime: Totals,
+}
+
+impl Totals {
+    /// Adds `other` on top, as if it had been recorded here all along.
+    pub fn add(&mut self, other: &Totals) {
+        self.clicks += other.clicks;
+        self.upgrades_bought += other.upgrades_bought;
+        self.gold_from_clicks += other.gold_from_clicks;
+        self.gold_from_income += other.gold_from_income;
+        self.gold_from_events += other.gold_from_events;
+        self.gold_from_waves += other.gold_from_waves;
+        for (event, lost) in &other.defense_lost {
+            *self.defense_lost.entry(event.clone()).or_default() += lost;
+        }
+        self.defense_lost_to_waves += other.defense_lost_to_waves;
+        self.events_survived += other.events_survived;
+        self.waves_repelled += other.waves_repelled;
+        self.peak_defense = self.peak_defense.max(other.peak_defense);
+        self.seconds_played += other.seconds_played;
+        self.castles_fallen += other.castles_fallen;
+    }
+}
+
+impl Statistics {
+    /// Applies `update` to the run and the lifetime totals alike.
+    pub fn record(&mut self, update: impl Fn(&mut Totals)) {
+        update(&mut self.run);
+        update(&mut self.lifetime);
     }
 }
 
+/// Unlocked achievements and when they were unlocked, kept across runs.
+#[derive(Component, Debug, Default, Serialize, Deserialize)]
+pub struct Achievements {
+    pub unlocked: HashMap<String, WallTime>,
+    /// Set on unlock until the achievements file has been written.
+    #[serde(skip)]
+    pub unsaved: bool,
+}
+
+/// The latest unlocked achievement and when it was unlocked, shown for a few seconds.
+#[derive(Component, Default)]
+pub struct AchievementToast(pub Option<(String, Instant)>);
+
+/// Index of the highlighted achievement on the achievements screen.
+#[derive(Component, Default)]
+pub struct SelectedAchievement(pub usize);
+
+/// What brought the castle down, once it has fallen.
+#[derive(Component, Default)]
+pub struct FatalBlow(pub Option<String>);
+
+/// A finished run as kept in the run history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub ended_at: WallTime,
+    #[serde(with = "crate::rng::seed_format")]
+    pub seed: u64,
+    /// Seconds the castle stood.
+    pub survived: f32,
+    pub gold_earned: u64,
+    pub upgrades_bought: u64,
+    pub events_survived: u32,
+    pub waves_repelled: u32,
+    pub fatal_blow: Option<String>,
+    pub score: u64,
+}
+
+/// The latest finished runs, oldest first, kept across runs.
+#[derive(Component, Debug, Default, Serialize, Deserialize)]
+pub struct RunHistory {
+    pub runs: Vec<RunRecord>,
+}
+
+/// A run ranked on the leaderboard under the config it was played with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub config_hash: String,
+    pub run: RunRecord,
+}
+
+/// The best runs of every config, best first, kept across runs.
+#[derive(Component, Debug, Default, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+/// Upgrade ids in shop order, built from the `[[upgrades]]` catalog.
+#[derive(Component)]
+pub struct Upgrades(pub Vec<String>);
+
 impl Upgrades {
-    pub fn apply_defense(&self, defense: &mut Defense, config: &Config) {
-        defense.0 += match self.0[0] {
-            AvailableUpgrades::Catapult => config.upgrades.catapult.defense,
-            AvailableUpgrades::Archer => config.upgrades.archer.defense,
-            AvailableUpgrades::Warrior => config.upgrades.warrior.defense,
-            AvailableUpgrades::Officer => config.upgrades.officer.defense,
-            _ => 0,
-        };
+    pub fn from_config(config: &Config) -> Self {
+        Self(
+            config
+                .upgrades
+                .iter()
+                .map(|upgrade| upgrade.id.clone())
+                .collect(),
+        )
     }
 }
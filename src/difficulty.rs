@@ -0,0 +1,155 @@
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::{events::EventContext, game::Config};
+
+/// Curves from the `[difficulty]` section; a missing curve stays at 1.0.
+#[derive(Debug, Default, Deserialize)]
+pub struct DifficultyConfig {
+    /// Scales defense damage rolled by events.
+    pub damage: Option<Curve>,
+    /// Divides `events.cooldown`, so events come more often.
+    pub frequency: Option<Curve>,
+    /// Scales the weights of hostile events against the rest of the table.
+    pub weights: Option<Curve>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveInput {
+    /// Seconds since the run started.
+    Time,
+    /// Gold currently in the treasury.
+    Wealth,
+}
+
+/// Piecewise linear multiplier, held flat before the first and after the last point.
+#[derive(Debug, Deserialize)]
+pub struct Curve {
+    pub by: CurveInput,
+    /// `[input, multiplier]` pairs sorted by input.
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Curve {
+    pub fn value(&self, context: &EventContext) -> f32 {
+        let input = match self.by {
+            CurveInput::Time => context.run_time.as_secs_f32(),
+            CurveInput::Wealth => context.gold as f32,
+        };
+
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if input <= first.0 {
+            return first.1;
+        }
+        if input >= last.0 {
+            return last.1;
+        }
+
+        let next = self.points.partition_point(|(x, _)| *x <= input);
+        let ((x0, y0), (x1, y1)) = (self.points[next - 1], self.points[next]);
+        y0 + (y1 - y0) * (input - x0) / (x1 - x0)
+    }
+}
+
+/// The difficulty multipliers at one moment of a run.
+#[derive(Debug, Clone, Copy)]
+pub struct Difficulty {
+    pub damage: f32,
+    pub frequency: f32,
+    pub weights: f32,
+}
+
+impl Difficulty {
+    pub fn at(config: &Config, context: &EventContext) -> Self {
+        let curve = |curve: &Option<Curve>| curve.as_ref().map_or(1.0, |c| c.value(context));
+        Self {
+            damage: curve(&config.difficulty.damage),
+            frequency: curve(&config.difficulty.frequency),
+            weights: curve(&config.difficulty.weights),
+        }
+    }
+
+    pub fn event_cooldown(&self, config: &Config) -> Duration {
+        Duration::from_secs_f32(config.events.cooldown / self.frequency)
+    }
+
+    /// How much more damage per second to expect than at the start of a run.
+    pub fn threat(&self) -> f32 {
+        self.damage * self.frequency * self.weights
+    }
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let difficulty = &config.difficulty;
+    let curves = [
+        ("damage", &difficulty.damage),
+        ("frequency", &difficulty.frequency),
+        ("weights", &difficulty.weights),
+    ];
+
+    for (name, curve) in curves {
+        let Some(curve) = curve else {
+            continue;
+        };
+        if curve.points.is_empty() {
+            return Err(format!("difficulty.{name} has no points").into());
+        }
+        if curve.points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(format!("difficulty.{name} points must be sorted by input").into());
+        }
+        if curve
+            .points
+            .iter()
+            .any(|(_, multiplier)| *multiplier <= 0.0)
+        {
+            return Err(format!("difficulty.{name} multipliers must be positive").into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::BoughtUpgrades;
+
+    fn curve() -> Curve {
+        Curve {
+            by: CurveInput::Time,
+            points: vec![(60.0, 1.0), (120.0, 2.0), (240.0, 5.0)],
+        }
+    }
+
+    fn value_at(curve: &Curve, seconds: f32) -> f32 {
+        let bought_upgrades = BoughtUpgrades::default();
+        curve.value(&EventContext {
+            run_time: Duration::from_secs_f32(seconds),
+            gold: 0,
+            bought_upgrades: &bought_upgrades,
+        })
+    }
+
+    #[test]
+    fn curves_pass_through_their_points() {
+        let curve = curve();
+        for (input, multiplier) in &curve.points {
+            assert_eq!(value_at(&curve, *input), *multiplier);
+        }
+    }
+
+    #[test]
+    fn curves_interpolate_between_points() {
+        let curve = curve();
+        assert_eq!(value_at(&curve, 90.0), 1.5);
+        assert_eq!(value_at(&curve, 180.0), 3.5);
+    }
+
+    #[test]
+    fn curves_hold_flat_outside_their_points() {
+        let curve = curve();
+        assert_eq!(value_at(&curve, 0.0), 1.0);
+        assert_eq!(value_at(&curve, 10_000.0), 5.0);
+    }
+}
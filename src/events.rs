@@ -0,0 +1,505 @@
+use rand::Rng;
+use rust_i18n::t;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+use crate::{
+    combat::{self, CombatKind},
+    components::{ActiveModifiers, BoughtUpgrades, Modifier, ModifierTarget},
+    difficulty::Difficulty,
+    game::Config,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct Events {
+    pub cooldown: f32,
+    /// Weight of the tick passing without any event.
+    pub nothing_weight: f32,
+    pub table: Vec<EventConfig>,
+}
+
+/// One entry of the event table, declared as `[[events.table]]` in config.toml.
+#[derive(Debug, Deserialize)]
+pub struct EventConfig {
+    pub id: String,
+    pub message: String,
+    /// How the event attacks; the army's strength of that kind softens it.
+    pub kind: Option<CombatKind>,
+    /// Relative chance against the other available events and `nothing_weight`.
+    pub weight: f32,
+    #[serde(default)]
+    pub requires: EventConditions,
+    pub effects: Vec<EffectConfig>,
+}
+
+/// Every condition that is set must hold for the event to be rolled.
+#[derive(Debug, Default, Deserialize)]
+pub struct EventConditions {
+    /// Seconds since the run started.
+    pub min_time: Option<f32>,
+    pub min_gold: Option<u64>,
+    /// Id of an upgrade that must be owned.
+    pub upgrade: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EffectConfig {
+    /// Defense change rolled from `min..=max`; negative values are damage.
+    Defense {
+        min: i64,
+        max: i64,
+    },
+    /// Gold change rolled from `min..=max`; negative values are losses.
+    Gold {
+        min: i64,
+        max: i64,
+    },
+    /// Scales a per-second rate for `duration` seconds.
+    Multiplier {
+        target: ModifierTarget,
+        factor: f32,
+        duration: f32,
+    },
+    DestroyUnits {
+        upgrade: String,
+        count: u32,
+    },
+}
+
+/// An effect with its random ranges already rolled.
+#[derive(Debug, Clone)]
+pub enum Effect {
+    Defense(i64),
+    Gold(i64),
+    Multiplier {
+        target: ModifierTarget,
+        factor: f32,
+        duration: Duration,
+    },
+    DestroyUnits {
+        upgrade: String,
+        count: u32,
+    },
+}
+
+#[derive(Debug)]
+pub struct GameEvent {
+    pub id: String,
+    pub kind: Option<CombatKind>,
+    pub message: String,
+    pub effects: Vec<Effect>,
+}
+
+/// What an event can look at when deciding whether it may happen.
+pub struct EventContext<'a> {
+    pub run_time: Duration,
+    pub gold: u64,
+    pub bought_upgrades: &'a BoughtUpgrades,
+}
+
+/// The parts of the castle an event can change.
+pub struct EventTarget<'a> {
+    pub gold: &'a mut u64,
+    pub defense: &'a mut u64,
+    /// Wall capacity; defense gains stop there.
+    pub max_defense: u64,
+    /// Share of defense damage that gets past prestige perks.
+    pub damage_factor: f32,
+    pub bought_upgrades: &'a mut BoughtUpgrades,
+    pub modifiers: &'a mut ActiveModifiers,
+}
+
+/// True chances of every outcome of a single event roll.
+pub struct EventOdds<'a> {
+    /// Available events with the probability of each being picked.
+    pub events: Vec<(&'a EventConfig, f32)>,
+    pub nothing: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct EventOutcome {
+    pub gold_gained: u64,
+    pub gold_lost: u64,
+    pub defense_gained: u64,
+    pub defense_lost: u64,
+    /// Defense damage the army prevented.
+    pub defense_mitigated: u64,
+    pub units_destroyed: u32,
+    pub castle_fell: bool,
+    /// Human readable description of every applied effect.
+    pub summary: Vec<String>,
+}
+
+impl EventConfig {
+    pub fn is_available(&self, context: &EventContext) -> bool {
+        let conditions = &self.requires;
+        conditions
+            .min_time
+            .is_none_or(|min_time| context.run_time.as_secs_f32() >= min_time)
+            && conditions.min_gold.is_none_or(|gold| context.gold >= gold)
+            && conditions
+                .upgrade
+                .as_ref()
+                .is_none_or(|upgrade| context.bought_upgrades.get_count(upgrade) > 0)
+    }
+
+    /// Events that can only hurt the castle; their weights follow the difficulty.
+    pub fn is_hostile(&self) -> bool {
+        self.effects.iter().all(|effect| match effect {
+            EffectConfig::Defense { max, .. } | EffectConfig::Gold { max, .. } => *max <= 0,
+            EffectConfig::Multiplier { factor, .. } => *factor < 1.0,
+            EffectConfig::DestroyUnits { .. } => true,
+        })
+    }
+
+    fn roll(&self, rng: &mut impl Rng, difficulty: Difficulty) -> GameEvent {
+        let effects = self
+            .effects
+            .iter()
+            .map(|effect| match effect {
+                EffectConfig::Defense { min, max } => {
+                    let amount = rng.random_range(*min..=*max);
+                    if amount < 0 {
+                        Effect::Defense((amount as f32 * difficulty.damage).round() as i64)
+                    } else {
+                        Effect::Defense(amount)
+                    }
+                }
+                EffectConfig::Gold { min, max } => Effect::Gold(rng.random_range(*min..=*max)),
+                EffectConfig::Multiplier {
+                    target,
+                    factor,
+                    duration,
+                } => Effect::Multiplier {
+                    target: *target,
+                    factor: *factor,
+                    duration: Duration::from_secs_f32(*duration),
+                },
+                EffectConfig::DestroyUnits { upgrade, count } => Effect::DestroyUnits {
+                    upgrade: upgrade.clone(),
+                    count: *count,
+                },
+            })
+            .collect();
+
+        GameEvent {
+            id: self.id.clone(),
+            kind: self.kind,
+            message: self.message.clone(),
+            effects,
+        }
+    }
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if config.events.nothing_weight < 0.0 {
+        return Err("events.nothing_weight must not be negative".into());
+    }
+    if config.events.cooldown <= 0.0 {
+        return Err("events.cooldown must be positive".into());
+    }
+
+    for event in &config.events.table {
+        if event.weight < 0.0 {
+            return Err(format!("event {} has a negative weight", event.id).into());
+        }
+        if let Some(upgrade) = &event.requires.upgrade {
+            if config.upgrade(upgrade).is_none() {
+                return Err(
+                    format!("event {} requires unknown upgrade {upgrade}", event.id).into(),
+                );
+            }
+        }
+
+        for effect in &event.effects {
+            match effect {
+                EffectConfig::Defense { min, max } | EffectConfig::Gold { min, max }
+                    if min > max =>
+                {
+                    return Err(format!("event {} has an empty effect range", event.id).into());
+                }
+                EffectConfig::Multiplier {
+                    factor, duration, ..
+                } if *factor < 0.0 || *duration < 0.0 => {
+                    return Err(format!(
+                        "event {} has a negative multiplier factor or duration",
+                        event.id
+                    )
+                    .into());
+                }
+                EffectConfig::DestroyUnits { upgrade, .. } if config.upgrade(upgrade).is_none() => {
+                    return Err(
+                        format!("event {} destroys unknown upgrade {upgrade}", event.id).into(),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Picks one of the available events, or none, in proportion to their weights.
+pub fn generate_random_event(
+    config: &Config,
+    context: &EventContext,
+    rng: &mut impl Rng,
+) -> Option<GameEvent> {
+    let difficulty = Difficulty::at(config, context);
+    let mut roll = rng.random::<f32>();
+    for (event, chance) in event_odds(config, context).events {
+        if roll < chance {
+            return Some(event.roll(rng, difficulty));
+        }
+        roll -= chance;
+    }
+    None
+}
+
+/// The probabilities `generate_random_event` picks with for this context.
+pub fn event_odds<'a>(config: &'a Config, context: &EventContext) -> EventOdds<'a> {
+    let difficulty = Difficulty::at(config, context);
+    let available: Vec<(&EventConfig, f32)> = config
+        .events
+        .table
+        .iter()
+        .filter(|event| event.is_available(context))
+        .map(|event| {
+            let scale = if event.is_hostile() {
+                difficulty.weights
+            } else {
+                1.0
+            };
+            (event, event.weight * scale)
+        })
+        .collect();
+    let total =
+        config.events.nothing_weight + available.iter().map(|(_, weight)| weight).sum::<f32>();
+    if total <= 0.0 {
+        return EventOdds {
+            events: Vec::new(),
+            nothing: 1.0,
+        };
+    }
+
+    EventOdds {
+        events: available
+            .into_iter()
+            .map(|(event, weight)| (event, weight / total))
+            .collect(),
+        nothing: config.events.nothing_weight / total,
+    }
+}
+
+pub fn apply_event(
+    event: &GameEvent,
+    target: EventTarget,
+    now: Instant,
+    config: &Config,
+) -> EventOutcome {
+    let mut outcome = EventOutcome::default();
+    let mitigation = combat::mitigation(&event.id, event.kind, target.bought_upgrades, config);
+
+    for effect in &event.effects {
+        match effect {
+            Effect::Defense(amount) if *amount < 0 => {
+                let damage = amount.unsigned_abs();
+                let loss =
+                    (damage as f32 * (1.0 - mitigation) * target.damage_factor).round() as u64;
+                outcome.defense_mitigated += damage - loss;
+                if *target.defense < loss {
+                    outcome.defense_lost += *target.defense;
+                    *target.defense = 0;
+                    outcome.castle_fell = true;
+                    break;
+                }
+                *target.defense -= loss;
+                outcome.defense_lost += loss;
+                outcome
+                    .summary
+                    .push(t!("game.events.effects.defense_loss", amount = loss).to_string());
+            }
+            Effect::Defense(amount) => {
+                let gain = (*amount as u64).min(target.max_defense.saturating_sub(*target.defense));
+                *target.defense += gain;
+                outcome.defense_gained += gain;
+                outcome
+                    .summary
+                    .push(t!("game.events.effects.defense_gain", amount = gain).to_string());
+            }
+            Effect::Gold(amount) if *amount < 0 => {
+                let loss = amount.unsigned_abs().min(*target.gold);
+                *target.gold -= loss;
+                outcome.gold_lost += loss;
+                outcome
+                    .summary
+                    .push(t!("game.events.effects.gold_loss", amount = loss).to_string());
+            }
+            Effect::Gold(amount) => {
+                let gain = *amount as u64;
+                *target.gold += gain;
+                outcome.gold_gained += gain;
+                outcome
+                    .summary
+                    .push(t!("game.events.effects.gold_gain", amount = gain).to_string());
+            }
+            Effect::Multiplier {
+                target: modifier_target,
+                factor,
+                duration,
+            } => {
+                target.modifiers.0.push(Modifier {
+                    target: *modifier_target,
+                    factor: *factor,
+                    expires_at: now + *duration,
+                });
+                outcome.summary.push(
+                    t!(
+                        "game.events.effects.multiplier",
+                        factor = factor,
+                        target = t!(modifier_target.i18n_key()),
+                        time = duration.as_secs()
+                    )
+                    .to_string(),
+                );
+            }
+            Effect::DestroyUnits { upgrade, count } => {
+                let destroyed = target.bought_upgrades.remove(upgrade, *count);
+                if destroyed == 0 {
+                    continue;
+                }
+                // Destroyed units take the defense they brought with them
+                let unit_defense = config.upgrade(upgrade).map_or(0, |unit| unit.defense);
+                let loss = (unit_defense * destroyed as u64).min(*target.defense);
+                *target.defense -= loss;
+                outcome.defense_lost += loss;
+                outcome.units_destroyed += destroyed;
+                let name = config
+                    .upgrade(upgrade)
+                    .map_or(upgrade.as_str(), |unit| &unit.name);
+                outcome.summary.push(
+                    t!(
+                        "game.events.effects.destroyed",
+                        count = destroyed,
+                        name = name
+                    )
+                    .to_string(),
+                );
+            }
+        }
+    }
+
+    if outcome.defense_mitigated > 0 && !outcome.castle_fell {
+        outcome.summary.push(
+            t!(
+                "game.events.effects.mitigated",
+                amount = outcome.defense_mitigated
+            )
+            .to_string(),
+        );
+    }
+
+    outcome
+}
+
+/// Builds the text shown in the event area.
+pub fn describe_event(event: &GameEvent, outcome: &EventOutcome) -> String {
+    if outcome.castle_fell {
+        t!("game.events.castle_fall", message = event.message).to_string()
+    } else if outcome.summary.is_empty() {
+        event.message.clone()
+    } else {
+        t!(
+            "game.events.summary",
+            message = event.message,
+            effects = outcome.summary.join(", ")
+        )
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn context(gold: u64, bought_upgrades: &BoughtUpgrades) -> EventContext<'_> {
+        EventContext {
+            run_time: Duration::ZERO,
+            gold,
+            bought_upgrades,
+        }
+    }
+
+    fn total(odds: &EventOdds) -> f32 {
+        odds.nothing + odds.events.iter().map(|(_, chance)| chance).sum::<f32>()
+    }
+
+    #[test]
+    fn the_odds_of_every_outcome_sum_to_one() {
+        let config = fixture::config();
+        let bought_upgrades = BoughtUpgrades::default();
+
+        for gold in [0, 1_000] {
+            let odds = event_odds(&config, &context(gold, &bought_upgrades));
+            assert!((total(&odds) - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn nothing_is_weighed_against_the_available_events() {
+        let config = fixture::config();
+        let bought_upgrades = BoughtUpgrades::default();
+
+        let odds = event_odds(&config, &context(1_000, &bought_upgrades));
+
+        let weights: f32 = config.events.table.iter().map(|event| event.weight).sum();
+        let nothing_weight = config.events.nothing_weight;
+        assert_eq!(odds.events.len(), config.events.table.len());
+        assert!((odds.nothing - nothing_weight / (weights + nothing_weight)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unavailable_events_have_no_odds() {
+        let config = fixture::config();
+        let bought_upgrades = BoughtUpgrades::default();
+        let tax = config.events.table.iter().find(|event| event.id == "tax");
+        let min_gold = tax.unwrap().requires.min_gold.unwrap();
+
+        let odds = event_odds(&config, &context(min_gold - 1, &bought_upgrades));
+
+        assert!(odds.events.iter().all(|(event, _)| event.id != "tax"));
+        assert_eq!(odds.events.len(), config.events.table.len() - 1);
+    }
+
+    #[test]
+    fn the_cooldown_must_be_positive() {
+        for cooldown in ["0.0", "-1.0"] {
+            let settings =
+                fixture::CONFIG.replace("cooldown = 2.0", &format!("cooldown = {cooldown}"));
+            assert!(Config::parse(&settings).is_err());
+        }
+    }
+
+    #[test]
+    fn multipliers_must_not_be_negative() {
+        let multiplier = |factor: f32, duration: f32| {
+            let plague = format!(
+                r#"
+                [[events.table]]
+                id = "plague"
+                message = "Plague"
+                weight = 5
+                effects = [{{ type = "multiplier", target = "gold_per_second", factor = {factor:?}, duration = {duration:?} }}]
+                "#
+            );
+            Config::parse(&format!("{}{plague}", fixture::CONFIG))
+        };
+
+        assert!(multiplier(0.5, 30.0).is_ok());
+        assert!(multiplier(-0.5, 30.0).is_err());
+        assert!(multiplier(0.5, -30.0).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! A small config for tests, so they do not break whenever config.toml is rebalanced.
+
+use crate::{components::RunRecord, game::Config, save::WallTime};
+
+pub const CONFIG: &str = r#"
+[[upgrades]]
+id = "catapult"
+icon = "C"
+name = "Catapult"
+cost = { base = 40, rate = 1.5 }
+defense = 6
+combat = { ranged = 2, siege = 4 }
+counters = [{ event = "siege", mitigation = 0.1 }]
+
+[[upgrades]]
+id = "archer"
+icon = "A"
+name = "Archer"
+cost = { base = 20, rate = 1.5 }
+defense = 4
+combat = { melee = 1, ranged = 2 }
+counters = [{ event = "raid", mitigation = 0.1 }]
+
+[[upgrades]]
+id = "warrior"
+icon = "W"
+name = "Warrior"
+cost = { base = 20, rate = 1.05 }
+defense = 3
+combat = { melee = 2 }
+
+[[upgrades]]
+id = "officer"
+icon = "O"
+name = "Officer"
+cost = { base = 50, exponent = 2.0 }
+requires = [{ gold_earned = 400 }]
+defense = 2
+defense_per_second = 3
+
+[[upgrades]]
+id = "oil"
+icon = "L"
+name = "Oil"
+cost = { base = 60, rate = 1.5 }
+requires = [{ survived = 60.0 }]
+defense = 7
+max_defense = 10
+combat = { melee = 4, siege = 4 }
+
+[[upgrades]]
+id = "walls"
+icon = "B"
+name = "Walls"
+cost = { base = 30, rate = 1.5 }
+defense = 5
+max_defense = 20
+
+[[upgrades]]
+id = "masons"
+icon = "M"
+name = "Masons"
+cost = { base = 30, rate = 2.0 }
+repair_speed = 3
+max_level = 2
+
+[[upgrades]]
+id = "trade_hall"
+icon = "T"
+name = "Trade hall"
+cost = { base = 100, multipliers = [1.0, 2.0, 3.0] }
+gold_per_second = { base = 4, multipliers = [1.0, 2.0, 3.0] }
+
+[[upgrades]]
+id = "midas_hand"
+icon = "H"
+name = "Midas hand"
+cost = { base = 200, multipliers = [1.0, 2.0] }
+click_cooldown_reduction = 0.2
+requires = [{ upgrade = "trade_hall", level = 3 }]
+
+[start]
+gold = 300
+defense = 80
+
+[walls]
+capacity = 120
+
+[repair]
+cost_per_point = 2.0
+speed = 4
+batch = 20
+
+[prestige]
+path = "prestige.toml"
+gold_per_point = 100.0
+seconds_per_point = 60.0
+
+[[prestige.perks]]
+id = "royal_charter"
+icon = "R"
+name = "Royal charter"
+bonus = "gold_per_second"
+amount = 0.2
+cost = { base = 1, rate = 2.0 }
+
+[[prestige.perks]]
+id = "golden_pick"
+icon = "G"
+name = "Golden pick"
+bonus = "click_value"
+amount = 2.0
+cost = { base = 2, rate = 2.0 }
+max_level = 3
+
+[achievements]
+path = "achievements.toml"
+
+[[achievements.table]]
+id = "first_coin"
+icon = "1"
+condition = { gold_earned = 1 }
+
+[[achievements.table]]
+id = "golden_touch"
+icon = "H"
+condition = { maxed = "midas_hand" }
+
+[history]
+path = "history.toml"
+keep = 4
+shown = 2
+
+[score]
+per_second = 2.0
+per_gold = 0.5
+per_event = 5.0
+per_wave = 20.0
+leaderboard = "leaderboard.toml"
+leaderboard_size = 3
+
+[click]
+base_cooldown = 1.0
+
+[sell]
+refund = 0.5
+
+[save]
+path = "save.toml"
+autosave_interval = 10.0
+statistics_path = "statistics.toml"
+
+[offline]
+max_duration = 600.0
+simulate_events = true
+
+[waves]
+first_wave = 90.0
+interval = 60.0
+reward = 1.0
+
+[[waves.enemies]]
+id = "bandits"
+icon = "B"
+name = "Bandits"
+strength = 3
+count = 4
+per_wave = 2.0
+
+[[waves.enemies]]
+id = "rams"
+icon = "R"
+name = "Rams"
+strength = 10
+count = 1
+per_wave = 0.5
+from_wave = 3
+
+[combat]
+mitigation_per_point = 0.01
+max_mitigation = 0.5
+
+[events]
+cooldown = 2.0
+nothing_weight = 40
+
+[[events.table]]
+id = "raid"
+message = "Raid"
+kind = "melee"
+weight = 30
+effects = [{ type = "defense", min = -10, max = -5 }]
+
+[[events.table]]
+id = "siege"
+message = "Siege"
+kind = "siege"
+weight = 10
+effects = [{ type = "defense", min = -20, max = -10 }]
+
+[[events.table]]
+id = "caravan"
+message = "Caravan"
+weight = 20
+effects = [{ type = "gold", min = 10, max = 20 }]
+
+[[events.table]]
+id = "tax"
+message = "Tax"
+weight = 10
+requires = { min_gold = 500 }
+effects = [{ type = "gold", min = -20, max = -10 }]
+"#;
+
+pub fn config() -> Config {
+    Config::parse(CONFIG).expect("the test config is valid")
+}
+
+/// A finished run that only differs from the others by its seed and score.
+pub fn finished_run(seed: u64, score: u64) -> RunRecord {
+    RunRecord {
+        ended_at: WallTime(0),
+        seed,
+        survived: 0.0,
+        gold_earned: 0,
+        upgrades_bought: 0,
+        events_survived: 0,
+        waves_repelled: 0,
+        fatal_blow: None,
+        score,
+    }
+}
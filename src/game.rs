@@ -1,72 +1,375 @@
 use bevy_ecs::prelude::*;
 use rand::Rng;
+use rust_i18n::t;
 use serde::Deserialize;
 use std::{
     fs,
     time::{Duration, Instant},
 };
 
-use crate::components;
+use crate::{
+    achievements::{self, AchievementsConfig},
+    combat::{self, CombatConfig, CombatStats, Counter},
+    components,
+    difficulty::{self, Difficulty, DifficultyConfig},
+    events::{self, EventContext, EventTarget},
+    history::{self, HistoryConfig},
+    prestige::{self, PrestigeConfig},
+    repair::{self, RepairConfig},
+    score::{self, ScoreConfig},
+    start::{self, StartConfig},
+    ui,
+    upgrade_effects::EffectRegistry,
+    waves::{self, WaveComposition, WavesConfig},
+};
 
 #[derive(Debug, Deserialize, Component)]
 pub struct Config {
-    pub upgrades: ConfigUpgrades,
-    pub events: Events,
-    pub trade: Trade,
-    pub trade_hall: TradeHallConfig,
-    pub midas_hand: MidasHandConfig,
+    pub start: StartConfig,
+    pub upgrades: Vec<UpgradeConfig>,
+    pub events: events::Events,
+    #[serde(default)]
+    pub difficulty: DifficultyConfig,
+    pub waves: WavesConfig,
+    pub combat: CombatConfig,
+    pub walls: WallsConfig,
+    pub repair: RepairConfig,
+    pub prestige: PrestigeConfig,
+    pub achievements: AchievementsConfig,
+    pub history: HistoryConfig,
+    pub score: ScoreConfig,
     pub click: ClickConfig,
+    pub sell: SellConfig,
+    pub save: SaveConfig,
+    pub offline: OfflineConfig,
+    #[serde(default)]
+    pub rng: RngConfig,
+    /// Built from `upgrades` on load.
+    #[serde(skip)]
+    pub effects: EffectRegistry,
+    /// Fingerprint of the settings, taken on load.
+    #[serde(skip)]
+    pub hash: String,
 }
 
 impl Config {
     pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
         let config_str = fs::read_to_string("config.toml")?;
-        let config: Config = toml::from_str(&config_str)?;
+        Self::parse(&config_str)
+    }
+
+    pub fn parse(config_str: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config: Config = toml::from_str(config_str)?;
+        config.validate()?;
+        config.effects = EffectRegistry::from_upgrades(&config.upgrades);
+        config.hash = score::config_hash(config_str)?;
         Ok(config)
     }
-}
 
-#[derive(Debug, Deserialize)]
-pub struct ConfigUpgrades {
-    pub catapult: UpgradeConfig,
-    pub archer: UpgradeConfig,
-    pub warrior: UpgradeConfig,
-    pub officer: UpgradeConfig,
-    pub oil: UpgradeConfig,
+    pub fn upgrade(&self, id: &str) -> Option<&UpgradeConfig> {
+        self.upgrades.iter().find(|upgrade| upgrade.id == id)
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.upgrades.is_empty() {
+            return Err("at least one upgrade must be configured".into());
+        }
+
+        for (index, upgrade) in self.upgrades.iter().enumerate() {
+            if self.upgrades[..index]
+                .iter()
+                .any(|other| other.id == upgrade.id)
+            {
+                return Err(format!("duplicate upgrade id {}", upgrade.id).into());
+            }
+            for requirement in &upgrade.requires {
+                if let Requirement::Upgrade {
+                    upgrade: required, ..
+                } = requirement
+                {
+                    if self.upgrade(required).is_none() {
+                        return Err(format!(
+                            "upgrade {} requires unknown upgrade {required}",
+                            upgrade.id
+                        )
+                        .into());
+                    }
+                }
+            }
+            if let Some(problem) = upgrade.cost.problem() {
+                return Err(format!("upgrade {} {problem}", upgrade.id).into());
+            }
+            if let CostCurve::Table { multipliers, .. } = &upgrade.cost {
+                if upgrade
+                    .max_level
+                    .is_some_and(|max| max as usize > multipliers.len())
+                {
+                    return Err(format!(
+                        "upgrade {} has a max_level beyond its cost table",
+                        upgrade.id
+                    )
+                    .into());
+                }
+            }
+            if upgrade.max_level == Some(0) {
+                return Err(format!("upgrade {} has a max_level of 0", upgrade.id).into());
+            }
+            let rates = [&upgrade.defense_per_second, &upgrade.gold_per_second];
+            if let Some(max_level) = upgrade.max_level() {
+                if rates.iter().any(|rate| rate.levels() < max_level as usize) {
+                    return Err(format!(
+                        "upgrade {} has fewer per-second levels than its max_level",
+                        upgrade.id
+                    )
+                    .into());
+                }
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.sell.refund) {
+            return Err("sell.refund must be between 0 and 1".into());
+        }
+        if self.save.autosave_interval <= 0.0 {
+            return Err("save.autosave_interval must be positive".into());
+        }
+        if self.offline.max_duration < 0.0 {
+            return Err("offline.max_duration must not be negative".into());
+        }
+
+        events::validate(self)?;
+        difficulty::validate(self)?;
+        waves::validate(self)?;
+        repair::validate(self)?;
+        prestige::validate(self)?;
+        achievements::validate(self)?;
+        history::validate(self)?;
+        score::validate(self)?;
+        start::validate(self)?;
+        combat::validate(self)
+    }
 }
 
+/// One entry of the upgrade shop, declared as `[[upgrades]]` in config.toml.
 #[derive(Debug, Deserialize)]
 pub struct UpgradeConfig {
-    pub cost: u64,
-    pub defense: u64,
-    pub defense_per_second: u64,
+    pub id: String,
+    pub icon: String,
     pub name: String,
+    pub cost: CostCurve,
+    /// Defense added immediately on every purchase, up to the wall capacity.
+    #[serde(default)]
+    pub defense: u64,
+    /// Wall capacity added per owned unit.
+    #[serde(default)]
+    pub max_defense: u64,
+    #[serde(default)]
+    pub defense_per_second: PerLevel,
+    #[serde(default)]
+    pub gold_per_second: PerLevel,
+    /// Seconds taken off the click cooldown per level.
+    #[serde(default)]
+    pub click_cooldown_reduction: f32,
+    /// Repair points mended per second, per owned unit.
+    #[serde(default)]
+    pub repair_speed: u64,
+    /// Strength of each unit, against events of the same kind and siege waves.
+    #[serde(default)]
+    pub combat: CombatStats,
+    #[serde(default)]
+    pub counters: Vec<Counter>,
+    #[serde(default)]
+    pub requires: Vec<Requirement>,
+    pub max_level: Option<u32>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct TradeHallConfig {
-    pub base_cost: u64,
-    pub base_gold_per_second: u64,
-    pub levels: Vec<TradeHallLevel>,
+impl UpgradeConfig {
+    /// Price of the next purchase when `level` are already owned.
+    pub fn cost(&self, level: u32) -> u64 {
+        self.cost.price(level)
+    }
+
+    /// Levelled upgrades stop at `max_level` or at the end of their cost table.
+    pub fn max_level(&self) -> Option<u32> {
+        match &self.cost {
+            CostCurve::Table { multipliers, .. } => {
+                Some(self.max_level.unwrap_or(multipliers.len() as u32))
+            }
+            _ => self.max_level,
+        }
+    }
+
+    pub fn is_maxed(&self, level: u32) -> bool {
+        self.max_level().is_some_and(|max| level >= max)
+    }
+
+    /// Whether the shop shows a level instead of an owned amount.
+    pub fn is_levelled(&self) -> bool {
+        self.max_level().is_some()
+    }
 }
 
 #[derive(Debug, Deserialize)]
-pub struct TradeHallLevel {
-    pub cost_multiplier: f32,
-    pub gold_multiplier: f32,
+#[serde(untagged)]
+pub enum CostCurve {
+    Flat(u64),
+    /// `base * multipliers[level]`, one multiplier per level.
+    Table {
+        base: u64,
+        multipliers: Vec<f32>,
+    },
+    /// `base * rate^owned`.
+    Exponential {
+        base: u64,
+        rate: f64,
+    },
+    /// `base * (owned + 1)^exponent`.
+    Polynomial {
+        base: u64,
+        exponent: f64,
+    },
 }
 
+impl CostCurve {
+    pub fn price(&self, level: u32) -> u64 {
+        match self {
+            CostCurve::Flat(cost) => *cost,
+            CostCurve::Table { base, multipliers } => {
+                let index = (level as usize).min(multipliers.len() - 1);
+                (*base as f32 * multipliers[index]) as u64
+            }
+            CostCurve::Exponential { base, rate } => {
+                (*base as f64 * rate.powi(level as i32)).round() as u64
+            }
+            CostCurve::Polynomial { base, exponent } => {
+                (*base as f64 * (level as f64 + 1.0).powf(*exponent)).round() as u64
+            }
+        }
+    }
+
+    /// Why the curve cannot price a purchase, if it cannot.
+    pub fn problem(&self) -> Option<&'static str> {
+        match self {
+            CostCurve::Table { multipliers, .. } if multipliers.is_empty() => {
+                Some("has an empty cost table")
+            }
+            CostCurve::Exponential { rate, .. } if *rate < 1.0 => Some("has a cost rate below 1"),
+            CostCurve::Polynomial { exponent, .. } if *exponent < 0.0 => {
+                Some("has a negative cost exponent")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A per-second effect, either per owned unit or looked up by level.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PerLevel {
+    PerUnit(u64),
+    /// `base * multipliers[level - 1]`, replacing rather than stacking.
+    Table {
+        base: u64,
+        multipliers: Vec<f32>,
+    },
+}
+
+impl Default for PerLevel {
+    fn default() -> Self {
+        Self::PerUnit(0)
+    }
+}
+
+impl PerLevel {
+    /// Levels with a distinct value; a per-unit amount never runs out.
+    pub fn levels(&self) -> usize {
+        match self {
+            PerLevel::PerUnit(_) => usize::MAX,
+            PerLevel::Table { multipliers, .. } => multipliers.len(),
+        }
+    }
+
+    pub fn value(&self, level: u32) -> u64 {
+        match self {
+            PerLevel::PerUnit(amount) => amount * level as u64,
+            PerLevel::Table { .. } if level == 0 => 0,
+            PerLevel::Table { base, multipliers } => {
+                let index = (level as usize - 1).min(multipliers.len().saturating_sub(1));
+                multipliers
+                    .get(index)
+                    .map_or(0, |multiplier| (*base as f32 * multiplier) as u64)
+            }
+        }
+    }
+}
+
+/// Something that must be achieved before an upgrade can be bought.
 #[derive(Debug, Deserialize)]
-pub struct MidasHandConfig {
-    pub required_trade_hall_level: u32,
-    pub base_cost: u64,
-    pub cooldown_reduction: f32,
-    pub levels: Vec<MidasHandLevel>,
+#[serde(untagged)]
+pub enum Requirement {
+    /// Another upgrade owned at `level` or above.
+    Upgrade { upgrade: String, level: u32 },
+    /// Gold earned over the run, spent or not.
+    GoldEarned { gold_earned: u64 },
+    /// Seconds the castle has stood this run.
+    Survived { survived: f32 },
+}
+
+impl Requirement {
+    pub fn is_met(&self, progress: &UnlockProgress) -> bool {
+        match self {
+            Requirement::Upgrade { upgrade, level } => {
+                progress.bought_upgrades.get_count(upgrade) >= *level
+            }
+            Requirement::GoldEarned { gold_earned } => progress.gold_earned >= *gold_earned,
+            Requirement::Survived { survived } => progress.run_time.as_secs_f32() >= *survived,
+        }
+    }
+
+    /// What is still missing, for the shop and the purchase refusal.
+    pub fn describe(&self, progress: &UnlockProgress, config: &Config) -> String {
+        match self {
+            Requirement::Upgrade { upgrade, level } => {
+                let name = config
+                    .upgrade(upgrade)
+                    .map_or(upgrade.as_str(), |required| &required.name);
+                t!(
+                    "game.upgrades.requirements.upgrade",
+                    name = name,
+                    level = level,
+                    current = progress.bought_upgrades.get_count(upgrade)
+                )
+                .to_string()
+            }
+            Requirement::GoldEarned { gold_earned } => t!(
+                "game.upgrades.requirements.gold_earned",
+                amount = gold_earned,
+                current = progress.gold_earned
+            )
+            .to_string(),
+            Requirement::Survived { survived } => {
+                let left = Duration::from_secs_f32(*survived).saturating_sub(progress.run_time);
+                t!(
+                    "game.upgrades.requirements.survived",
+                    time = ui::format_duration(Duration::from_secs_f32(*survived)),
+                    left = ui::format_duration(left)
+                )
+                .to_string()
+            }
+        }
+    }
+}
+
+/// How far the run has come, as far as requirements are concerned.
+pub struct UnlockProgress<'a> {
+    pub bought_upgrades: &'a components::BoughtUpgrades,
+    pub gold_earned: u64,
+    pub run_time: Duration,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct MidasHandLevel {
-    pub cost_multiplier: f32,
+pub struct WallsConfig {
+    /// Wall capacity of a castle without upgrades.
+    pub capacity: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -75,125 +378,441 @@ pub struct ClickConfig {
 }
 
 #[derive(Debug, Deserialize)]
-pub struct Events {
-    pub bandit_raid: EventConfig,
-    pub siege_catapults: EventConfig,
-    pub sabotage: EventConfig,
-    pub cooldown: f32,
+pub struct SellConfig {
+    /// Share of the unit's price paid back when it is sold.
+    pub refund: f32,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct EventConfig {
-    pub min_damage: u64,
-    pub max_damage: u64,
-    pub probability: f32,
-    pub message: String,
+pub struct SaveConfig {
+    pub path: String,
+    pub autosave_interval: f32,
+    /// File the lifetime statistics are kept in; unlike the save it outlives a fallen castle.
+    pub statistics_path: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Trade {
-    pub offer: TradeConfig,
+#[derive(Debug, Default, Deserialize)]
+pub struct RngConfig {
+    pub seed: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct TradeConfig {
-    pub min_gain: u64,
-    pub max_gain: u64,
-    pub probability: f32,
-    pub message: String,
+pub struct OfflineConfig {
+    pub max_duration: f32,
+    pub simulate_events: bool,
+}
+
+pub fn calculate_defense_per_second(
+    bought_upgrades: &components::BoughtUpgrades,
+    config: &Config,
+) -> u64 {
+    config.effects.defense_per_second(bought_upgrades)
+}
+
+/// How much defense the walls can hold; defense never regenerates past it.
+pub fn calculate_max_defense(bought_upgrades: &components::BoughtUpgrades, config: &Config) -> u64 {
+    config.walls.capacity + config.effects.max_defense(bought_upgrades)
+}
+
+pub fn calculate_gold_per_second(
+    bought_upgrades: &components::BoughtUpgrades,
+    config: &Config,
+) -> u64 {
+    config.effects.gold_per_second(bought_upgrades)
 }
 
-#[derive(Debug)]
-pub enum GameEvent {
-    BanditRaid { defense_loss: u64, message: String },
-    SiegeCatapults { defense_loss: u64, message: String },
-    Sabotage { defense_loss: u64, message: String },
-    TradeOffer { gold_gain: u64, message: String },
-    Nothing,
+#[derive(Debug, Default)]
+pub struct OfflineProgress {
+    pub elapsed: Duration,
+    pub credited: Duration,
+    pub gold_gained: u64,
+    pub gold_lost: u64,
+    pub defense_gained: u64,
+    pub defense_lost: u64,
+    pub units_destroyed: u32,
+    pub events: u32,
+    pub waves: u32,
+    /// Number of the wave still to come and how long until it arrives.
+    pub next_wave: u32,
+    pub until_wave: Duration,
+    pub castle_fell: bool,
+    /// What the time away adds to the statistics.
+    pub totals: components::Totals,
 }
 
-pub fn generate_random_event(config: &Config) -> GameEvent {
-    let mut rng = rand::rng();
+/// Castle state at the moment the game was saved.
+pub struct OfflineStart {
+    pub gold: u64,
+    pub defense: u64,
+    /// Prestige bonuses, see [`prestige::gold_factor`] and [`prestige::damage_factor`].
+    pub gold_factor: f32,
+    pub damage_factor: f32,
+    pub run_time: Duration,
+    pub wave: u32,
+    pub until_wave: Duration,
+}
 
-    if rng.random_bool(config.events.bandit_raid.probability as f64) {
-        GameEvent::BanditRaid {
-            defense_loss: rng.random_range(
-                config.events.bandit_raid.min_damage..=config.events.bandit_raid.max_damage,
-            ),
-            message: config.events.bandit_raid.message.clone(),
+/// Replays the time the game was closed one second at a time, the same way
+/// `update_per_second_system`, `handle_events_system` and `wave_system` would have.
+/// Units destroyed by events are removed from `bought_upgrades`, and paid
+/// repairs are worked off `repairs`.
+pub fn calculate_offline_progress(
+    elapsed: Duration,
+    start: OfflineStart,
+    bought_upgrades: &mut components::BoughtUpgrades,
+    repairs: &mut components::RepairQueue,
+    config: &Config,
+    rng: &mut impl Rng,
+) -> OfflineProgress {
+    let credited = elapsed.min(Duration::from_secs_f32(config.offline.max_duration));
+
+    let mut progress = OfflineProgress {
+        elapsed,
+        credited,
+        next_wave: start.wave,
+        until_wave: start.until_wave,
+        ..Default::default()
+    };
+    let mut next_wave_at = start.until_wave;
+    let mut gold = start.gold;
+    let mut defense = start.defense;
+    let mut modifiers = components::ActiveModifiers::default();
+    // Modifiers only need instants relative to each other
+    let origin = Instant::now();
+    let mut since_event = 0.0;
+
+    for second in 1..=credited.as_secs() {
+        let now = origin + Duration::from_secs(second);
+        let gold_ps = calculate_gold_per_second(bought_upgrades, config) as f32
+            * modifiers.factor(components::ModifierTarget::GoldPerSecond, now)
+            * start.gold_factor;
+        let defense_ps = calculate_defense_per_second(bought_upgrades, config) as f32
+            * modifiers.factor(components::ModifierTarget::DefensePerSecond, now);
+        modifiers.prune(now);
+
+        let max_defense = calculate_max_defense(bought_upgrades, config);
+        let mut regenerated = (defense_ps as u64).min(max_defense.saturating_sub(defense));
+        defense += regenerated;
+        regenerated += repair::work(
+            repairs,
+            &mut defense,
+            max_defense,
+            repair::repair_speed(bought_upgrades, config),
+        );
+        gold += gold_ps as u64;
+        progress.gold_gained += gold_ps as u64;
+        progress.defense_gained += regenerated;
+        progress.totals.gold_from_income += gold_ps as u64;
+        progress.totals.peak_defense = progress.totals.peak_defense.max(defense);
+
+        if !config.offline.simulate_events {
+            continue;
         }
-    } else if rng.random_bool(config.events.siege_catapults.probability as f64) {
-        GameEvent::SiegeCatapults {
-            defense_loss: rng.random_range(
-                config.events.siege_catapults.min_damage..=config.events.siege_catapults.max_damage,
-            ),
-            message: config.events.siege_catapults.message.clone(),
+
+        if Duration::from_secs(second) >= next_wave_at {
+            let composition = WaveComposition::new(progress.next_wave, config);
+            let outcome = waves::resolve_wave(
+                &composition,
+                &mut gold,
+                &mut defense,
+                bought_upgrades,
+                config,
+            );
+            progress.waves += 1;
+            progress.gold_gained += outcome.reward;
+            progress.defense_lost += outcome.defense_lost;
+            progress.next_wave += 1;
+            next_wave_at += waves::wave_interval(config);
+            progress.totals.gold_from_waves += outcome.reward;
+            progress.totals.defense_lost_to_waves += outcome.defense_lost;
+            if outcome.castle_fell {
+                progress.castle_fell = true;
+                progress.totals.castles_fallen += 1;
+                break;
+            }
+            progress.totals.waves_repelled += 1;
         }
-    } else if rng.random_bool(config.events.sabotage.probability as f64) {
-        GameEvent::Sabotage {
-            defense_loss: rng.random_range(
-                config.events.sabotage.min_damage..=config.events.sabotage.max_damage,
-            ),
-            message: config.events.sabotage.message.clone(),
+
+        let context = EventContext {
+            run_time: start.run_time + Duration::from_secs(second),
+            gold,
+            bought_upgrades,
+        };
+        let cooldown = Difficulty::at(config, &context)
+            .event_cooldown(config)
+            .as_secs_f32();
+        since_event += 1.0;
+        if since_event < cooldown {
+            continue;
         }
-    } else if rng.random_bool(config.trade.offer.probability as f64) {
-        GameEvent::TradeOffer {
-            gold_gain: rng.random_range(config.trade.offer.min_gain..=config.trade.offer.max_gain),
-            message: config.trade.offer.message.clone(),
+        since_event -= cooldown;
+
+        let Some(event) = events::generate_random_event(config, &context, rng) else {
+            continue;
+        };
+
+        let target = EventTarget {
+            gold: &mut gold,
+            defense: &mut defense,
+            max_defense,
+            damage_factor: start.damage_factor,
+            bought_upgrades,
+            modifiers: &mut modifiers,
+        };
+        let outcome = events::apply_event(&event, target, now, config);
+        progress.events += 1;
+        progress.gold_gained += outcome.gold_gained;
+        progress.gold_lost += outcome.gold_lost;
+        progress.defense_gained += outcome.defense_gained;
+        progress.defense_lost += outcome.defense_lost;
+        progress.units_destroyed += outcome.units_destroyed;
+        progress.totals.gold_from_events += outcome.gold_gained;
+        *progress
+            .totals
+            .defense_lost
+            .entry(event.id.clone())
+            .or_default() += outcome.defense_lost;
+        if outcome.castle_fell {
+            progress.castle_fell = true;
+            progress.totals.castles_fallen += 1;
+            break;
         }
-    } else {
-        GameEvent::Nothing
+        progress.totals.events_survived += 1;
     }
-}
 
-pub fn calculate_defense_per_second(
-    bought_upgrades: &components::BoughtUpgrades,
-    config: &Config,
-) -> u64 {
-    let level = bought_upgrades.get_count(&components::AvailableUpgrades::Officer);
-    if level == 0 {
-        return 0;
+    // Without simulated attacks the wave countdown waits for the player
+    if config.offline.simulate_events {
+        progress.until_wave = next_wave_at.saturating_sub(credited);
     }
 
-    config.upgrades.officer.defense_per_second * level as u64
+    progress
 }
 
-pub fn calculate_gold_per_second(
+pub fn calculate_click_cooldown(
     bought_upgrades: &components::BoughtUpgrades,
     config: &Config,
-) -> u64 {
-    let level = bought_upgrades.get_count(&components::AvailableUpgrades::TradeHall);
-    if level == 0 {
-        return 0;
-    }
-    let level_index = (level - 1) as usize;
-    let level = &config.trade_hall.levels[level_index];
-    (config.trade_hall.base_gold_per_second as f32 * level.gold_multiplier) as u64
-}
-
-pub fn calculate_click_cooldown(midas_level: u32, config: &Config) -> f32 {
-    let base_cooldown = config.click.base_cooldown;
-    let reduction = if midas_level > 0 {
-        let level_index = (midas_level - 1) as usize;
-        if level_index < config.midas_hand.levels.len() {
-            midas_level as f32 * config.midas_hand.cooldown_reduction
-        } else {
-            0.0
-        }
-    } else {
-        0.0
-    };
-    base_cooldown - reduction
+) -> f32 {
+    let reduction = config.effects.click_cooldown_reduction(bought_upgrades);
+    (config.click.base_cooldown - reduction).max(0.0)
+}
+
+/// Returns the first requirement of `upgrade` that is not met yet.
+pub fn missing_requirement<'a>(
+    upgrade: &'a UpgradeConfig,
+    progress: &UnlockProgress,
+) -> Option<&'a Requirement> {
+    upgrade
+        .requires
+        .iter()
+        .find(|requirement| !requirement.is_met(progress))
 }
 
 pub fn can_afford_upgrade(gold: u64, cost: u64) -> bool {
     gold >= cost
 }
 
-pub fn calculate_next_event_cooldown(last_event_check: Instant, config: &Config) -> f32 {
-    let now = Instant::now();
-    let next_event_at =
-        last_event_check + Duration::from_millis((config.events.cooldown * 1000.0).round() as u64);
+/// Max mode never buys more than this at once.
+pub const MAX_BATCH: u32 = 1000;
+
+/// Units a purchase would buy and what they cost together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Batch {
+    pub count: u32,
+    pub cost: u64,
+}
+
+/// Sizes a purchase of `upgrade` at `level`. Fixed batches stop at the level
+/// cap; max mode buys what `gold` covers, quoting a single unit when that is
+/// nothing so the shop still shows the next price.
+pub fn plan_batch(
+    upgrade: &UpgradeConfig,
+    level: u32,
+    gold: u64,
+    quantity: components::BuyQuantity,
+) -> Batch {
+    let remaining = upgrade
+        .max_level()
+        .map_or(u32::MAX, |max| max.saturating_sub(level));
+
+    let mut batch = Batch { count: 0, cost: 0 };
+    let limit = quantity.count().unwrap_or(MAX_BATCH).min(remaining);
+    while batch.count < limit {
+        // Steep curves can run past what a u64 holds long before the limit
+        let Some(total) = batch.cost.checked_add(upgrade.cost(level + batch.count)) else {
+            break;
+        };
+        if quantity.count().is_none() && total > gold {
+            break;
+        }
+        batch.cost = total;
+        batch.count += 1;
+    }
+
+    if batch.count == 0 && remaining > 0 {
+        return Batch {
+            count: 1,
+            cost: upgrade.cost(level),
+        };
+    }
+    batch
+}
+
+pub fn calculate_next_event_cooldown(
+    last_event_check: Instant,
+    now: Instant,
+    cooldown: Duration,
+) -> f32 {
+    let next_event_at = last_event_check + cooldown;
 
     next_event_at.duration_since(now).as_secs_f32()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{BoughtUpgrades, BuyQuantity, RepairQueue},
+        fixture,
+        rng::GameRng,
+    };
+
+    fn upgrade(settings: &str) -> UpgradeConfig {
+        toml::from_str(&format!(
+            "id = \"test\"\nicon = \"\"\nname = \"Test\"\n{settings}"
+        ))
+        .expect("test upgrade should parse")
+    }
+
+    fn parse_with(setting: &str, value: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        let (key, _) = setting.split_once(" = ").unwrap();
+        assert!(fixture::CONFIG.contains(setting));
+        Config::parse(&fixture::CONFIG.replace(setting, &format!("{key} = {value}")))
+    }
+
+    #[test]
+    fn the_autosave_interval_must_be_positive() {
+        assert!(parse_with("autosave_interval = 10.0", "0.0").is_err());
+        assert!(parse_with("autosave_interval = 10.0", "-5.0").is_err());
+    }
+
+    #[test]
+    fn the_offline_max_duration_must_not_be_negative() {
+        assert!(parse_with("max_duration = 600.0", "0.0").is_ok());
+        assert!(parse_with("max_duration = 600.0", "-1.0").is_err());
+    }
+
+    #[test]
+    fn batches_stop_before_their_cost_overflows() {
+        let steep = upgrade("cost = { base = 1000, rate = 1000.0 }");
+
+        for quantity in [BuyQuantity::Hundred, BuyQuantity::Max] {
+            let batch = plan_batch(&steep, 0, u64::MAX, quantity);
+            let expected: u64 = (0..batch.count).map(|level| steep.cost(level)).sum();
+            assert!(batch.count > 0 && batch.count < 100);
+            assert_eq!(batch.cost, expected);
+        }
+    }
+
+    fn offline_start(defense: u64, until_wave: Duration) -> OfflineStart {
+        OfflineStart {
+            gold: 0,
+            defense,
+            gold_factor: 1.0,
+            damage_factor: 1.0,
+            run_time: Duration::ZERO,
+            wave: 1,
+            until_wave,
+        }
+    }
+
+    fn away(
+        elapsed: Duration,
+        start: OfflineStart,
+        bought_upgrades: &mut BoughtUpgrades,
+        config: &Config,
+    ) -> OfflineProgress {
+        let mut repairs = RepairQueue::default();
+        let mut rng = GameRng::new(0);
+        calculate_offline_progress(
+            elapsed,
+            start,
+            bought_upgrades,
+            &mut repairs,
+            config,
+            &mut rng,
+        )
+    }
+
+    fn trade_hall() -> BoughtUpgrades {
+        let mut bought_upgrades = BoughtUpgrades::default();
+        bought_upgrades.increment("trade_hall");
+        bought_upgrades
+    }
+
+    #[test]
+    fn offline_time_is_credited_up_to_max_duration() {
+        let mut config = fixture::config();
+        config.offline.simulate_events = false;
+        let max_duration = Duration::from_secs_f32(config.offline.max_duration);
+        let mut bought_upgrades = trade_hall();
+        let income = calculate_gold_per_second(&bought_upgrades, &config);
+
+        let start = offline_start(config.start.defense, Duration::from_secs(60));
+        let progress = away(max_duration * 3, start, &mut bought_upgrades, &config);
+
+        assert_eq!(progress.elapsed, max_duration * 3);
+        assert_eq!(progress.credited, max_duration);
+        assert_eq!(progress.gold_gained, income * max_duration.as_secs());
+        assert_eq!(progress.totals.gold_from_income, progress.gold_gained);
+    }
+
+    #[test]
+    fn nothing_attacks_while_away_unless_simulated() {
+        let mut config = fixture::config();
+        config.offline.simulate_events = false;
+        let until_wave = Duration::from_secs_f32(config.waves.first_wave);
+        let mut bought_upgrades = BoughtUpgrades::default();
+
+        let start = offline_start(config.start.defense, until_wave);
+        let progress = away(until_wave * 3, start, &mut bought_upgrades, &config);
+
+        assert_eq!((progress.events, progress.waves), (0, 0));
+        assert_eq!((progress.next_wave, progress.until_wave), (1, until_wave));
+        assert_eq!(progress.defense_lost, 0);
+        assert!(!progress.castle_fell);
+    }
+
+    #[test]
+    fn a_castle_can_fall_while_away() {
+        let config = fixture::config();
+        let max_duration = Duration::from_secs_f32(config.offline.max_duration);
+        let mut bought_upgrades = BoughtUpgrades::default();
+
+        // Leave the castle to the events alone
+        let start = offline_start(1, max_duration * 2);
+        let progress = away(max_duration, start, &mut bought_upgrades, &config);
+
+        assert!(progress.castle_fell);
+        assert_eq!(progress.waves, 0);
+        assert_eq!(progress.totals.castles_fallen, 1);
+        // The event that took the castle was not survived
+        assert_eq!(progress.totals.events_survived + 1, progress.events);
+    }
+
+    #[test]
+    fn offline_gold_is_recorded_in_the_statistics() {
+        let config = fixture::config();
+        let max_duration = Duration::from_secs_f32(config.offline.max_duration);
+        let mut bought_upgrades = trade_hall();
+
+        let start = offline_start(config.walls.capacity, Duration::ZERO);
+        let progress = away(max_duration, start, &mut bought_upgrades, &config);
+
+        let totals = &progress.totals;
+        assert!(progress.events > 0 && progress.waves > 0);
+        assert_eq!(
+            totals.gold_from_income + totals.gold_from_events + totals.gold_from_waves,
+            progress.gold_gained
+        );
+    }
+}
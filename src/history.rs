@@ -0,0 +1,136 @@
+use bevy_ecs::prelude::*;
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::{
+    clock::GameClock,
+    components::{
+        FatalBlow, GoldEarned, Leaderboard, RunHistory, RunRecord, RunStarted, Statistics,
+    },
+    game::Config,
+    rng::GameRng,
+    save::{self, WallTime},
+    score,
+};
+
+/// The `[history]` section: finished runs kept for the game over screen.
+#[derive(Debug, Deserialize)]
+pub struct HistoryConfig {
+    /// File the run history is kept in.
+    pub path: String,
+    /// Finished runs kept; the oldest are dropped first.
+    pub keep: usize,
+    /// Best runs listed on the game over screen.
+    pub shown: usize,
+}
+
+/// Adds `run` to the history, dropping the oldest runs beyond `keep`.
+pub fn record(history: &mut RunHistory, run: RunRecord, config: &Config) {
+    history.runs.push(run);
+    let excess = history.runs.len().saturating_sub(config.history.keep);
+    history.runs.drain(..excess);
+}
+
+/// The `count` best scoring runs, best first; ties go to the earlier run.
+pub fn best(history: &RunHistory, count: usize) -> Vec<&RunRecord> {
+    let mut runs: Vec<&RunRecord> = history.runs.iter().collect();
+    runs.sort_by_key(|run| std::cmp::Reverse(run.score));
+    runs.truncate(count);
+    runs
+}
+
+/// Sums up and scores the fallen castle, then adds it to the history and the leaderboard.
+pub fn record_run(world: &mut World) {
+    let now = world.resource::<GameClock>().now();
+    let seed = world.resource::<GameRng>().seed();
+    let mut query = world.query::<(
+        &mut RunHistory,
+        &mut Leaderboard,
+        &GoldEarned,
+        &RunStarted,
+        &Statistics,
+        &FatalBlow,
+        &Config,
+    )>();
+    let (mut history, mut leaderboard, gold_earned, run_started, statistics, fatal_blow, config) =
+        query.single_mut(world);
+
+    let survived = now.duration_since(run_started.0).as_secs_f32();
+    let mut run = RunRecord {
+        ended_at: WallTime::now(),
+        seed,
+        survived,
+        gold_earned: gold_earned.0,
+        upgrades_bought: statistics.run.upgrades_bought,
+        events_survived: statistics.run.events_survived,
+        waves_repelled: statistics.run.waves_repelled,
+        fatal_blow: fatal_blow.0.clone(),
+        score: 0,
+    };
+    run.score = score::score(&run, config);
+    score::submit(&mut leaderboard, run.clone(), config);
+    record(&mut history, run, config);
+}
+
+pub fn save_history(world: &mut World) -> Result<(), Box<dyn Error>> {
+    let (history, config) = world.query::<(&RunHistory, &Config)>().single(world);
+    save::write_toml(&config.history.path, history)
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn Error>> {
+    if config.history.keep == 0 || config.history.shown == 0 {
+        return Err("history.keep and history.shown must be at least 1".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn history_keeps_the_latest_runs_and_ranks_them_by_score() {
+        let config = fixture::config();
+        let keep = config.history.keep;
+        let mut history = RunHistory::default();
+        for seed in 0..keep as u64 + 2 {
+            // Odd runs score higher so the ranking differs from the order of play
+            let run = fixture::finished_run(seed, seed % 2 * 100 + seed);
+            record(&mut history, run, &config);
+        }
+
+        assert_eq!(history.runs.len(), keep);
+        assert_eq!(history.runs[0].seed, 2);
+        let best = best(&history, 3);
+        assert_eq!(best.len(), 3);
+        assert!(best.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(
+            best[0].score,
+            history.runs.iter().map(|run| run.score).max().unwrap()
+        );
+    }
+
+    #[test]
+    fn runs_with_seeds_past_the_range_of_toml_integers_are_kept() {
+        let config = fixture::config();
+        let mut history = RunHistory::default();
+        record(
+            &mut history,
+            fixture::finished_run(u64::MAX - 5, 10),
+            &config,
+        );
+        let mut leaderboard = Leaderboard::default();
+        score::submit(
+            &mut leaderboard,
+            fixture::finished_run(u64::MAX - 5, 10),
+            &config,
+        );
+
+        let history: RunHistory = toml::from_str(&toml::to_string(&history).unwrap()).unwrap();
+        let leaderboard: Leaderboard =
+            toml::from_str(&toml::to_string(&leaderboard).unwrap()).unwrap();
+        assert_eq!(history.runs[0].seed, u64::MAX - 5);
+        assert_eq!(leaderboard.entries[0].run.seed, u64::MAX - 5);
+    }
+}
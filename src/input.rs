@@ -1,14 +1,17 @@
-use bevy_ecs::prelude::*;
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use std::time::{Duration, Instant};
+use rust_i18n::t;
+use std::time::Duration;
 
 use crate::{
+    clock::GameClock,
     components::{
-        AvailableUpgrades, BoughtUpgrades, Defense, DefensePerSecond, EventMessage, GameRunning,
-        GameState, Gold, GoldPerSecond, LastClick, LastEventCheck, MaxDefense, SelectedUpgrade,
-        Upgrades,
+        BoughtUpgrades, BuyQuantity, ConfirmAbandon, EventMessage, GameRunning, GameState, Gold,
+        GoldEarned, LastClick, OfflineReport, Prestige, RunStarted, SelectedAchievement,
+        SelectedPerk, SelectedUpgrade, ShowEventOdds, Statistics, Upgrades,
     },
     game::{self, Config},
+    prestige, save, start, systems,
 };
 
 pub fn handle_input(world: &mut World, upgrade_schedule: &mut Schedule) -> std::io::Result<()> {
@@ -20,6 +23,13 @@ pub fn handle_input(world: &mut World, upgrade_schedule: &mut Schedule) -> std::
 
     if event::poll(Duration::from_millis(100))? {
         if let Event::Key(key) = event::read()? {
+            // Any key closes the "while you were away" report
+            let mut report_query = world.query::<&mut OfflineReport>();
+            let mut offline_report = report_query.single_mut(world);
+            if key.kind == KeyEventKind::Press && offline_report.0.take().is_some() {
+                return Ok(());
+            }
+
             match key.code {
                 KeyCode::Char('q') | KeyCode::Esc => {
                     let mut state_query = world.query::<(&mut GameRunning, &mut GameState)>();
@@ -28,42 +38,61 @@ pub fn handle_input(world: &mut World, upgrade_schedule: &mut Schedule) -> std::
                     *game_state = GameState::Exiting;
                 }
                 KeyCode::Char(' ') if key.kind == KeyEventKind::Press => {
-                    let mut click_query =
-                        world.query::<(&mut Gold, &mut LastClick, &Config, &BoughtUpgrades)>();
-
-                    if let Ok((mut gold, mut last_click, config, bought_upgrades)) =
-                        click_query.get_single_mut(world)
-                    {
-                        let now = Instant::now();
-                        let midas_hand = bought_upgrades.get_count(&AvailableUpgrades::MidasHand);
-                        let cooldown = game::calculate_click_cooldown(midas_hand, config);
-                        if now.duration_since(last_click.0).as_secs_f32() >= cooldown {
-                            gold.0 += 1;
-                            last_click.0 = now;
-                        }
-                    }
+                    handle_click(world);
                 }
                 KeyCode::Up | KeyCode::Char('k') => {
                     let mut query = world.query::<(&Upgrades, &mut SelectedUpgrade)>();
                     let (upgrades, mut selected) = query.single_mut(world);
 
-                    let current_index = upgrades.0.iter().position(|&u| u == selected.0).unwrap();
+                    let current_index = upgrades.0.iter().position(|u| *u == selected.0).unwrap();
                     if current_index > 0 {
-                        selected.0 = upgrades.0[current_index - 1];
+                        selected.0 = upgrades.0[current_index - 1].clone();
                     }
                 }
                 KeyCode::Down | KeyCode::Char('j') => {
                     let mut query = world.query::<(&Upgrades, &mut SelectedUpgrade)>();
                     let (upgrades, mut selected) = query.single_mut(world);
 
-                    let current_index = upgrades.0.iter().position(|&u| u == selected.0).unwrap();
+                    let current_index = upgrades.0.iter().position(|u| *u == selected.0).unwrap();
                     if current_index < upgrades.0.len() - 1 {
-                        selected.0 = upgrades.0[current_index + 1];
+                        selected.0 = upgrades.0[current_index + 1].clone();
                     }
                 }
                 KeyCode::Enter => {
                     upgrade_schedule.run(world);
                 }
+                KeyCode::Char('s') if key.kind == KeyEventKind::Press => {
+                    world
+                        .run_system_once(systems::handle_sell_system)
+                        .expect("sell system has no fallible parameters");
+                }
+                KeyCode::Char('r') if key.kind == KeyEventKind::Press => {
+                    world
+                        .run_system_once(systems::handle_repair_system)
+                        .expect("repair system has no fallible parameters");
+                }
+                KeyCode::Char('b') if key.kind == KeyEventKind::Press => {
+                    let mut query = world.query::<&mut BuyQuantity>();
+                    let mut quantity = query.single_mut(world);
+                    *quantity = quantity.next();
+                }
+                KeyCode::Char('a') if key.kind == KeyEventKind::Press => {
+                    *world.query::<&mut GameState>().single_mut(world) = GameState::Achievements;
+                }
+                KeyCode::Char('p') if key.kind == KeyEventKind::Press => {
+                    *world.query::<&mut GameState>().single_mut(world) = GameState::Prestige;
+                }
+                KeyCode::Char('t') if key.kind == KeyEventKind::Press => {
+                    *world.query::<&mut GameState>().single_mut(world) = GameState::Statistics;
+                }
+                KeyCode::Char('l') if key.kind == KeyEventKind::Press => {
+                    *world.query::<&mut GameState>().single_mut(world) = GameState::Leaderboard;
+                }
+                KeyCode::Char('d') if key.kind == KeyEventKind::Press => {
+                    let mut query = world.query::<&mut ShowEventOdds>();
+                    let mut show_event_odds = query.single_mut(world);
+                    show_event_odds.0 = !show_event_odds.0;
+                }
                 _ => {}
             }
         }
@@ -71,6 +100,204 @@ pub fn handle_input(world: &mut World, upgrade_schedule: &mut Schedule) -> std::
     Ok(())
 }
 
+/// Mines a coin by hand if the click cooldown has passed.
+pub fn handle_click(world: &mut World) {
+    let now = world.resource::<GameClock>().now();
+    let mut click_query = world.query::<(
+        &mut Gold,
+        &mut GoldEarned,
+        &mut LastClick,
+        &mut Statistics,
+        &BoughtUpgrades,
+        &Prestige,
+        &Config,
+    )>();
+
+    if let Ok((
+        mut gold,
+        mut gold_earned,
+        mut last_click,
+        mut statistics,
+        bought_upgrades,
+        prestige,
+        config,
+    )) = click_query.get_single_mut(world)
+    {
+        let cooldown = game::calculate_click_cooldown(bought_upgrades, config);
+        if now.duration_since(last_click.0).as_secs_f32() >= cooldown {
+            let value = prestige::click_value(prestige, config);
+            gold.0 += value;
+            gold_earned.0 += value;
+            last_click.0 = now;
+            statistics.record(|totals| {
+                totals.clicks += 1;
+                totals.gold_from_clicks += value;
+            });
+        }
+    }
+}
+
+pub fn handle_prestige_input(world: &mut World) -> std::io::Result<()> {
+    if event::poll(Duration::from_millis(100))? {
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                // Abandoning takes a [Y]; any other key calls it off
+                let mut confirm_abandon = world.query::<&mut ConfirmAbandon>().single_mut(world);
+                if std::mem::take(&mut confirm_abandon.0) {
+                    if key.code == KeyCode::Char('y') {
+                        abandon_castle(world);
+                    }
+                    return Ok(());
+                }
+
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('p') => {
+                        *world.query::<&mut GameState>().single_mut(world) = GameState::Playing;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        let mut selected = world.query::<&mut SelectedPerk>().single_mut(world);
+                        selected.0 = selected.0.saturating_sub(1);
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        let mut query = world.query::<(&mut SelectedPerk, &Config)>();
+                        let (mut selected, config) = query.single_mut(world);
+                        if selected.0 + 1 < config.prestige.perks.len() {
+                            selected.0 += 1;
+                        }
+                    }
+                    KeyCode::Enter => buy_perk(world),
+                    KeyCode::Char('a') => {
+                        world.query::<&mut ConfirmAbandon>().single_mut(world).0 = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_achievements_input(world: &mut World) -> std::io::Result<()> {
+    if event::poll(Duration::from_millis(100))? {
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('a') => {
+                        *world.query::<&mut GameState>().single_mut(world) = GameState::Playing;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        let mut selected =
+                            world.query::<&mut SelectedAchievement>().single_mut(world);
+                        selected.0 = selected.0.saturating_sub(1);
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        let mut query = world.query::<(&mut SelectedAchievement, &Config)>();
+                        let (mut selected, config) = query.single_mut(world);
+                        if selected.0 + 1 < config.achievements.table.len() {
+                            selected.0 += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_statistics_input(world: &mut World) -> std::io::Result<()> {
+    if event::poll(Duration::from_millis(100))? {
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press
+                && matches!(key.code, KeyCode::Esc | KeyCode::Char('t'))
+            {
+                *world.query::<&mut GameState>().single_mut(world) = GameState::Playing;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_leaderboard_input(world: &mut World) -> std::io::Result<()> {
+    if event::poll(Duration::from_millis(100))? {
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press
+                && matches!(key.code, KeyCode::Esc | KeyCode::Char('l'))
+            {
+                *world.query::<&mut GameState>().single_mut(world) = GameState::Playing;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn buy_perk(world: &mut World) {
+    let now = world.resource::<GameClock>().now();
+    let mut query = world.query::<(&mut Prestige, &mut EventMessage, &SelectedPerk, &Config)>();
+    let (mut prestige, mut event_message, selected, config) = query.single_mut(world);
+    let Some(perk) = config.prestige.perks.get(selected.0) else {
+        return;
+    };
+
+    event_message.timestamp = now;
+    event_message.message = match prestige::buy_perk(&mut prestige, perk) {
+        Some(cost) => t!("game.prestige.bought", name = perk.name, cost = cost).to_string(),
+        None if perk.is_maxed(prestige.level(&perk.id)) => {
+            t!("game.prestige.refused_maxed", name = perk.name).to_string()
+        }
+        None => t!(
+            "game.prestige.refused",
+            name = perk.name,
+            cost = perk.cost.price(prestige.level(&perk.id))
+        )
+        .to_string(),
+    };
+    store_prestige(world);
+}
+
+/// Trades the current castle for renown and starts over from scratch.
+fn abandon_castle(world: &mut World) {
+    let now = world.resource::<GameClock>().now();
+    let mut query = world.query::<(
+        &mut Prestige,
+        &mut EventMessage,
+        &GoldEarned,
+        &RunStarted,
+        &Config,
+    )>();
+    let (mut prestige, mut event_message, gold_earned, run_started, config) =
+        query.single_mut(world);
+
+    let gain = prestige::renown_for_run(gold_earned.0, now.duration_since(run_started.0), config);
+    event_message.timestamp = now;
+    if gain == 0 {
+        event_message.message = t!("game.prestige.nothing_to_gain").to_string();
+        return;
+    }
+
+    prestige.renown += gain;
+    prestige.castles_abandoned += 1;
+    reset_game(world);
+
+    world.query::<&mut EventMessage>().single_mut(world).message =
+        t!("game.prestige.abandoned_castle", amount = gain).to_string();
+    store_prestige(world);
+    // The old castle must not be restored along with the renown it paid
+    if save::save_world(world).is_err() {
+        world.query::<&mut EventMessage>().single_mut(world).message =
+            t!("game.save.failed").to_string();
+    }
+}
+
+/// Writes the prestige file, reporting a failure in the event bar.
+fn store_prestige(world: &mut World) {
+    let mut query = world.query::<(&Prestige, &mut EventMessage, &Config)>();
+    let (prestige, mut event_message, config) = query.single_mut(world);
+    if save::write_toml(&config.prestige.path, prestige).is_err() {
+        event_message.message = t!("game.prestige.save_failed").to_string();
+    }
+}
+
 pub fn handle_game_over_input(world: &mut World) -> std::io::Result<()> {
     if event::poll(Duration::from_millis(100))? {
         if let Event::Key(key) = event::read()? {
@@ -91,48 +318,7 @@ pub fn handle_game_over_input(world: &mut World) -> std::io::Result<()> {
     Ok(())
 }
 
-fn reset_game(world: &mut World) {
-    let mut query = world.query::<(
-        &mut Gold,
-        &mut GameState,
-        &mut GameRunning,
-        &mut LastClick,
-        &mut LastEventCheck,
-        &mut Defense,
-        &mut MaxDefense,
-        &mut GoldPerSecond,
-        &mut DefensePerSecond,
-        &mut EventMessage,
-        &mut Upgrades,
-        &mut SelectedUpgrade,
-    )>();
-
-    let (
-        mut gold,
-        mut game_state,
-        mut game_running,
-        mut last_click,
-        mut last_event_check,
-        mut defense,
-        mut max_defense,
-        mut gold_ps,
-        mut defense_ps,
-        mut event_message,
-        mut upgrades,
-        mut selected_upgrade,
-    ) = query.single_mut(world);
-
-    // Reset to initial game state
-    *gold = Gold(100);
-    *game_state = GameState::Playing;
-    *game_running = GameRunning(true);
-    *last_click = LastClick(Instant::now());
-    *last_event_check = LastEventCheck(Instant::now());
-    *defense = Defense(100);
-    *max_defense = MaxDefense(100);
-    *gold_ps = GoldPerSecond(0);
-    *defense_ps = DefensePerSecond(0);
-    *upgrades = Upgrades::default();
-    *selected_upgrade = SelectedUpgrade(AvailableUpgrades::Catapult);
-    event_message.message.clear();
+/// Starts a new castle after the old one fell or was abandoned.
+pub fn reset_game(world: &mut World) {
+    start::start_run(world);
 }
@@ -1,8 +1,25 @@
+mod achievements;
+mod clock;
+mod combat;
 mod components;
+mod difficulty;
+mod events;
+#[cfg(test)]
+mod fixture;
 mod game;
+mod history;
 mod input;
+mod prestige;
+mod repair;
+mod rng;
+mod save;
+mod score;
+mod simulation;
+mod start;
 mod systems;
 mod ui;
+mod upgrade_effects;
+mod waves;
 
 use bevy_ecs::prelude::*;
 use crossterm::{
@@ -10,101 +27,306 @@ use crossterm::{
     ExecutableCommand,
 };
 use ratatui::{prelude::*, Terminal};
-use std::{io, time::Instant};
+use rust_i18n::t;
+use std::{error::Error, io};
 
-use crate::components::AvailableUpgrades;
+use crate::{clock::GameClock, rng::GameRng};
 
 rust_i18n::i18n!("locales");
 
 #[derive(Bundle)]
 struct GameBundle {
-    gold: components::Gold,
-    gold_ps: components::GoldPerSecond,
-    defense: components::Defense,
-    max_defense: components::MaxDefense,
-    defense_ps: components::DefensePerSecond,
+    run: start::RunBundle,
     upgrades: components::Upgrades,
-    last_tick: components::LastTick,
-    last_event_check: components::LastEventCheck,
-    last_click: components::LastClick,
-    event_message: components::EventMessage,
-    selected_upgrade: components::SelectedUpgrade,
-    game_running: components::GameRunning,
-    game_state: components::GameState,
-    bought_upgrades: components::BoughtUpgrades,
+    buy_quantity: components::BuyQuantity,
+    show_event_odds: components::ShowEventOdds,
+    prestige: components::Prestige,
+    selected_perk: components::SelectedPerk,
+    confirm_abandon: components::ConfirmAbandon,
+    statistics: components::Statistics,
+    achievements: components::Achievements,
+    achievement_toast: components::AchievementToast,
+    selected_achievement: components::SelectedAchievement,
+    run_history: components::RunHistory,
+    leaderboard: components::Leaderboard,
     config: game::Config,
 }
 
 fn main() -> io::Result<()> {
     rust_i18n::set_locale("ru");
 
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if_eq("simulate").is_some() {
+        return simulation::run(args).map_err(|err| io::Error::other(err.to_string()));
+    }
+    let cli_seed = parse_seed_arg(args);
+
     enable_raw_mode()?;
     io::stdout().execute(EnterAlternateScreen)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
 
-    let config = game::Config::load().expect("Failed to load game config");
-    let mut world = setup_world(config);
+    let mut config = game::Config::load().expect("Failed to load game config");
+    // Restarts seed from the same place as the first castle
+    config.rng.seed = cli_seed.or(config.rng.seed);
+    let rng = GameRng::for_new_run(config.rng.seed);
+    let mut world = setup_world(config, GameClock::Real, rng);
+    restore_world(&mut world);
+    // An explicit seed wins over the one stored in the save
+    if let Some(seed) = cli_seed {
+        world.insert_resource(GameRng::new(seed));
+    }
     let (mut schedule, mut upgrade_schedule) = setup_schedules();
+    schedule.add_systems(save::autosave_system.after(systems::wave_system));
+    schedule.add_systems(achievements::persist_system.after(systems::achievements_system));
+
+    let result = run_game(
+        &mut terminal,
+        &mut world,
+        &mut schedule,
+        &mut upgrade_schedule,
+    );
+
+    // The terminal is given back before any error is reported
+    disable_raw_mode()?;
+    io::stdout().execute(LeaveAlternateScreen)?;
+    let previous_state = result?;
+
+    // Quitting from the game over screen leaves nothing worth restoring
+    if previous_state.in_run() {
+        save::save_world(&mut world).map_err(|err| io::Error::other(err.to_string()))?;
+    }
 
-    // Game loop
+    Ok(())
+}
+
+/// Runs frames until the player quits, returning the state shown last.
+fn run_game(
+    terminal: &mut Terminal<impl Backend>,
+    world: &mut World,
+    schedule: &mut Schedule,
+    upgrade_schedule: &mut Schedule,
+) -> io::Result<components::GameState> {
+    let mut previous_state = components::GameState::Playing;
     loop {
-        let game_state = world.query::<&components::GameState>().single(&world);
+        let game_state = *world.query::<&components::GameState>().single(world);
 
-        match *game_state {
+        match game_state {
             components::GameState::Playing => {
-                schedule.run(&mut world);
-                render_game(&mut terminal, &mut world)?;
-                input::handle_input(&mut world, &mut upgrade_schedule)?;
+                schedule.run(world);
+                render_game(terminal, world)?;
+                input::handle_input(world, upgrade_schedule)?;
+            }
+            components::GameState::Prestige => {
+                // The castle keeps going while the player weighs abandoning it
+                schedule.run(world);
+                render_prestige(terminal, world)?;
+                input::handle_prestige_input(world)?;
+            }
+            components::GameState::Achievements => {
+                schedule.run(world);
+                render_achievements(terminal, world)?;
+                input::handle_achievements_input(world)?;
+            }
+            components::GameState::Statistics => {
+                schedule.run(world);
+                render_statistics(terminal, world)?;
+                input::handle_statistics_input(world)?;
+            }
+            components::GameState::Leaderboard => {
+                schedule.run(world);
+                render_leaderboard(terminal, world)?;
+                input::handle_leaderboard_input(world)?;
             }
             components::GameState::GameOver => {
-                render_game_over(&mut terminal, &mut world)?;
-                input::handle_game_over_input(&mut world)?;
+                if previous_state != components::GameState::GameOver {
+                    end_run(world);
+                }
+                render_game_over(terminal, world)?;
+                input::handle_game_over_input(world)?;
             }
             components::GameState::Exiting => {
-                break;
+                return Ok(previous_state);
             }
         }
+
+        previous_state = game_state;
     }
+}
 
-    disable_raw_mode()?;
-    io::stdout().execute(LeaveAlternateScreen)?;
+/// Files written when a castle falls, with the message shown if writing fails.
+type RunEndWrite = (fn(&mut World) -> Result<(), Box<dyn Error>>, &'static str);
 
-    Ok(())
+const RUN_END_WRITES: [RunEndWrite; 4] = [
+    (save::discard_world, "game.save.discard_failed"),
+    (save::save_statistics, "game.statistics.save_failed"),
+    (history::save_history, "game.history.save_failed"),
+    (score::save_leaderboard, "game.leaderboard.save_failed"),
+];
+
+/// Records the fallen castle and writes every file on its own, so one failure
+/// does not cost the others; failures are listed on the game over screen.
+fn end_run(world: &mut World) {
+    history::record_run(world);
+    let failures: Vec<String> = RUN_END_WRITES
+        .iter()
+        .filter(|(write, _)| write(world).is_err())
+        .map(|(_, message)| t!(*message).to_string())
+        .collect();
+    if failures.is_empty() {
+        return;
+    }
+
+    let now = world.resource::<GameClock>().now();
+    let mut event_message = world
+        .query::<&mut components::EventMessage>()
+        .single_mut(world);
+    event_message.message = failures.join("; ");
+    event_message.timestamp = now;
+}
+
+/// Reads `--seed <n>` from the command line.
+fn parse_seed_arg(mut args: impl Iterator<Item = String>) -> Option<u64> {
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            let seed = args.next().expect("--seed requires a value");
+            return Some(seed.parse().expect("--seed must be an unsigned integer"));
+        }
+    }
+    None
 }
 
-fn setup_world(config: game::Config) -> World {
+fn setup_world(config: game::Config, clock: GameClock, rng: GameRng) -> World {
     let mut world = World::new();
+    let now = clock.now();
+    world.insert_resource(clock);
+    world.insert_resource(rng);
 
     world.spawn(GameBundle {
-        gold: components::Gold(200),
-        gold_ps: components::GoldPerSecond(0),
-        defense: components::Defense(100),
-        max_defense: components::MaxDefense(100),
-        defense_ps: components::DefensePerSecond(0),
-        last_tick: components::LastTick(Instant::now()),
-        last_event_check: components::LastEventCheck(Instant::now()),
-        last_click: components::LastClick(Instant::now()),
-        event_message: components::EventMessage {
-            message: String::new(),
-            timestamp: Instant::now(),
-        },
-        selected_upgrade: components::SelectedUpgrade(AvailableUpgrades::Catapult),
-        bought_upgrades: components::BoughtUpgrades::default(),
-        upgrades: components::Upgrades::default(),
-        game_running: components::GameRunning(true),
-        game_state: components::GameState::default(),
+        run: start::RunBundle::new(&config, now),
+        upgrades: components::Upgrades::from_config(&config),
+        buy_quantity: components::BuyQuantity::default(),
+        show_event_odds: components::ShowEventOdds::default(),
+        prestige: components::Prestige::default(),
+        selected_perk: components::SelectedPerk::default(),
+        confirm_abandon: components::ConfirmAbandon::default(),
+        statistics: components::Statistics::default(),
+        achievements: components::Achievements::default(),
+        achievement_toast: components::AchievementToast::default(),
+        selected_achievement: components::SelectedAchievement::default(),
+        run_history: components::RunHistory::default(),
+        leaderboard: components::Leaderboard::default(),
         config,
     });
 
     world
 }
 
+/// Reads every file kept between launches. What carries over between runs
+/// comes first, since resuming the save credits the time away with it.
+fn restore_world(world: &mut World) {
+    restore_prestige(world);
+    restore_achievements(world);
+    restore_statistics(world);
+    restore_history(world);
+    restore_leaderboard(world);
+    restore_save(world);
+}
+
+fn restore_save(world: &mut World) {
+    let save_path = world
+        .query::<&game::Config>()
+        .single(world)
+        .save
+        .path
+        .clone();
+    match save::load(&save_path) {
+        Ok(Some(save)) => save.apply(world),
+        Ok(None) => {}
+        Err(_) => {
+            let mut query = world.query::<&mut components::EventMessage>();
+            query.single_mut(world).message = t!("game.save.load_failed").to_string();
+        }
+    }
+}
+
+fn restore_prestige(world: &mut World) {
+    let mut query = world.query::<(
+        &mut components::Prestige,
+        &mut components::EventMessage,
+        &game::Config,
+    )>();
+    let (mut prestige, mut event_message, config) = query.single_mut(world);
+    match save::load_toml(&config.prestige.path) {
+        Ok(Some(loaded)) => *prestige = loaded,
+        Ok(None) => {}
+        Err(_) => event_message.message = t!("game.prestige.load_failed").to_string(),
+    }
+}
+
+fn restore_achievements(world: &mut World) {
+    let mut query = world.query::<(
+        &mut components::Achievements,
+        &mut components::EventMessage,
+        &game::Config,
+    )>();
+    let (mut achievements, mut event_message, config) = query.single_mut(world);
+    match save::load_toml(&config.achievements.path) {
+        Ok(Some(loaded)) => *achievements = loaded,
+        Ok(None) => {}
+        Err(_) => event_message.message = t!("game.achievements.load_failed").to_string(),
+    }
+}
+
+fn restore_statistics(world: &mut World) {
+    let mut query = world.query::<(
+        &mut components::Statistics,
+        &mut components::EventMessage,
+        &game::Config,
+    )>();
+    let (mut statistics, mut event_message, config) = query.single_mut(world);
+    match save::load_toml(&config.save.statistics_path) {
+        Ok(Some(loaded)) => statistics.lifetime = loaded,
+        Ok(None) => {}
+        Err(_) => event_message.message = t!("game.statistics.load_failed").to_string(),
+    }
+}
+
+fn restore_history(world: &mut World) {
+    let mut query = world.query::<(
+        &mut components::RunHistory,
+        &mut components::EventMessage,
+        &game::Config,
+    )>();
+    let (mut history, mut event_message, config) = query.single_mut(world);
+    match save::load_toml(&config.history.path) {
+        Ok(Some(loaded)) => *history = loaded,
+        Ok(None) => {}
+        Err(_) => event_message.message = t!("game.history.load_failed").to_string(),
+    }
+}
+
+fn restore_leaderboard(world: &mut World) {
+    let mut query = world.query::<(
+        &mut components::Leaderboard,
+        &mut components::EventMessage,
+        &game::Config,
+    )>();
+    let (mut leaderboard, mut event_message, config) = query.single_mut(world);
+    match save::load_toml(&score::leaderboard_path(config)) {
+        Ok(Some(loaded)) => *leaderboard = loaded,
+        Ok(None) => {}
+        Err(_) => event_message.message = t!("game.leaderboard.load_failed").to_string(),
+    }
+}
+
 fn setup_schedules() -> (Schedule, Schedule) {
     let mut schedule = Schedule::default();
     schedule.add_systems(
         (
             systems::update_per_second_system,
             systems::handle_events_system,
+            systems::wave_system,
+            systems::achievements_system,
         )
             .chain(),
     );
@@ -116,84 +338,349 @@ fn setup_schedules() -> (Schedule, Schedule) {
 }
 
 fn render_game(terminal: &mut Terminal<impl Backend>, world: &mut World) -> io::Result<()> {
+    let now = world.resource::<GameClock>().now();
+    let seed = world.resource::<GameRng>().seed();
     let mut query = world.query::<(
-        &components::Gold,
+        (&components::Gold, &components::GoldEarned),
         &components::GoldPerSecond,
-        &components::Defense,
+        (
+            &components::Defense,
+            &components::MaxDefense,
+            &components::RepairQueue,
+        ),
         &components::DefensePerSecond,
         &components::Upgrades,
-        &components::SelectedUpgrade,
+        (&components::SelectedUpgrade, &components::BuyQuantity),
         &game::Config,
-        &components::EventMessage,
+        (&components::EventMessage, &components::AchievementToast),
         &components::LastClick,
         &components::LastEventCheck,
         &components::BoughtUpgrades,
+        &components::OfflineReport,
+        &components::RunStarted,
+        &components::ShowEventOdds,
+        &components::Wave,
     )>();
     let entity = query.get_single(world).unwrap();
 
     terminal
         .draw(|frame| {
-            let (event_area, stats_area) = ui::create_layout(frame.area());
-            let upgrades_area = Rect::new(
-                stats_area.x + stats_area.width,
-                stats_area.y,
-                stats_area.width,
-                stats_area.height,
-            );
+            let (event_area, stats_area, upgrades_area, wave_area) =
+                ui::create_layout(frame.area());
 
             let (
-                gold,
+                (gold, gold_earned),
                 gold_ps,
-                defense,
+                (defense, max_defense, repairs),
                 dps,
                 upgrades,
-                selected,
+                (selected, quantity),
                 config,
-                event,
+                (event, toast),
                 last_click,
                 last_event_check,
                 bought_upgrades,
+                offline_report,
+                run_started,
+                show_event_odds,
+                wave,
             ) = entity;
 
-            let midas_level = bought_upgrades.get_count(&AvailableUpgrades::MidasHand);
-            let click_cooldown = game::calculate_click_cooldown(midas_level, config);
-            let next_event_cooldown =
-                game::calculate_next_event_cooldown(last_event_check.0, config);
+            let context = events::EventContext {
+                run_time: now.duration_since(run_started.0),
+                gold: gold.0,
+                bought_upgrades,
+            };
+            let difficulty = difficulty::Difficulty::at(config, &context);
+
+            let click_cooldown = game::calculate_click_cooldown(bought_upgrades, config);
+            let next_event_cooldown = game::calculate_next_event_cooldown(
+                last_event_check.0,
+                now,
+                difficulty.event_cooldown(config),
+            );
 
             let stats = ui::stats::StatsData {
                 gold,
                 gold_ps,
                 defense,
+                max_defense,
                 dps,
+                repairs,
+                repair_speed: repair::repair_speed(bought_upgrades, config),
+                threat: difficulty.threat(),
+                army: combat::army_strength(bought_upgrades, config),
+                seed,
             };
             let timing = ui::stats::TimingData {
+                now,
                 last_click,
                 click_cooldown,
                 next_event_cooldown,
             };
             ui::stats::render_stats(frame, stats_area, stats, timing);
 
-            ui::upgrades::render_upgrades(
-                frame,
-                upgrades_area,
+            let progress = game::UnlockProgress {
+                bought_upgrades,
+                gold_earned: gold_earned.0,
+                run_time: context.run_time,
+            };
+            let shop = ui::upgrades::ShopData {
                 upgrades,
                 selected,
-                bought_upgrades,
+                quantity: *quantity,
+                gold: gold.0,
+            };
+            ui::upgrades::render_upgrades(frame, upgrades_area, shop, &progress, config);
+            let toasted = toast
+                .0
+                .as_ref()
+                .filter(|(_, at)| now.duration_since(*at) < ui::events::TOAST_DURATION)
+                .and_then(|(id, _)| config.achievements.table.iter().find(|a| a.id == *id));
+            match toasted {
+                Some(achievement) => {
+                    ui::events::render_achievement_toast(frame, event_area, achievement)
+                }
+                None => ui::events::render_event(frame, event_area, event),
+            }
+
+            let incoming = ui::waves::WaveData {
+                composition: waves::WaveComposition::new(wave.number, config),
+                garrison: waves::garrison_strength(bought_upgrades, config),
+                time_left: wave.next_at.saturating_duration_since(now),
+            };
+            ui::waves::render_wave(frame, wave_area, incoming);
+
+            if show_event_odds.0 {
+                let odds = events::event_odds(config, &context);
+                ui::events::render_event_odds(frame, frame.area(), &odds, config);
+            }
+
+            if let Some(progress) = &offline_report.0 {
+                ui::offline::render_offline_report(frame, frame.area(), progress);
+            }
+        })
+        .map(|_| ())
+}
+
+fn render_prestige(terminal: &mut Terminal<impl Backend>, world: &mut World) -> io::Result<()> {
+    let now = world.resource::<GameClock>().now();
+    let mut query = world.query::<(
+        &components::Prestige,
+        &components::SelectedPerk,
+        &components::ConfirmAbandon,
+        &components::GoldEarned,
+        &components::RunStarted,
+        &components::EventMessage,
+        &game::Config,
+    )>();
+    let (prestige, selected, confirm_abandon, gold_earned, run_started, event, config) =
+        query.single(world);
+
+    terminal
+        .draw(|frame| {
+            let data = ui::prestige::PrestigeData {
+                prestige,
+                selected: selected.0,
+                confirm_abandon: confirm_abandon.0,
+                gain: prestige::renown_for_run(
+                    gold_earned.0,
+                    now.duration_since(run_started.0),
+                    config,
+                ),
+                message: &event.message,
+            };
+            ui::prestige::render_prestige(frame, frame.area(), data, config);
+        })
+        .map(|_| ())
+}
+
+fn render_achievements(terminal: &mut Terminal<impl Backend>, world: &mut World) -> io::Result<()> {
+    let mut query = world.query::<(
+        &components::Achievements,
+        &components::SelectedAchievement,
+        &game::Config,
+    )>();
+    let (achievements, selected, config) = query.single(world);
+
+    terminal
+        .draw(|frame| {
+            ui::achievements::render_achievements(
+                frame,
+                frame.area(),
+                achievements,
+                selected.0,
                 config,
             );
-            ui::events::render_event(frame, event_area, event);
+        })
+        .map(|_| ())
+}
+
+fn render_statistics(terminal: &mut Terminal<impl Backend>, world: &mut World) -> io::Result<()> {
+    let mut query = world.query::<(&components::Statistics, &game::Config)>();
+    let (statistics, config) = query.single(world);
+
+    terminal
+        .draw(|frame| {
+            ui::statistics::render_statistics(frame, frame.area(), statistics, config);
+        })
+        .map(|_| ())
+}
+
+fn render_leaderboard(terminal: &mut Terminal<impl Backend>, world: &mut World) -> io::Result<()> {
+    let mut query = world.query::<(&components::Leaderboard, &game::Config)>();
+    let (leaderboard, config) = query.single(world);
+
+    terminal
+        .draw(|frame| {
+            ui::leaderboard::render_leaderboard(frame, frame.area(), leaderboard, config);
         })
         .map(|_| ())
 }
 
 fn render_game_over(terminal: &mut Terminal<impl Backend>, world: &mut World) -> io::Result<()> {
-    let mut query = world.query::<(&components::Defense, &components::MaxDefense)>();
-    let (defense, max_defense) = query.single_mut(world);
+    let mut query = world.query::<(
+        &components::RunHistory,
+        &components::EventMessage,
+        &game::Config,
+    )>();
+    let (run_history, event_message, config) = query.single(world);
 
     terminal
         .draw(|frame| {
             let area = ui::create_game_over_layout(frame.area());
-            ui::game_over::render_game_over(frame, area, defense, max_defense);
+            let data = ui::game_over::GameOverData {
+                run: run_history.runs.last(),
+                best: history::best(run_history, config.history.shown),
+                message: &event_message.message,
+            };
+            ui::game_over::render_game_over(frame, area, data);
         })
         .map(|_| ())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    /// The fixture config with every file it keeps moved into its own
+    /// temporary directory, since tests run in parallel.
+    fn config_in_temp_dir(test: &str) -> game::Config {
+        let dir =
+            std::env::temp_dir().join(format!("castle-clickers-{}-{test}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |file: &str| dir.join(file).to_string_lossy().into_owned();
+
+        let mut config = fixture::config();
+        config.save.path = path("save.toml");
+        config.save.statistics_path = path("statistics.toml");
+        config.prestige.path = path("prestige.toml");
+        config.achievements.path = path("achievements.toml");
+        config.history.path = path("history.toml");
+        config
+    }
+
+    fn remove_temp_dir(world: &mut World) {
+        let config = world.query::<&game::Config>().single(world);
+        let dir = std::path::Path::new(&config.save.path).parent().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Saves `world` as if the game had been closed `away` ago.
+    fn save_closed_for(world: &mut World, away: std::time::Duration) {
+        let mut save = save::SaveGame::from_world(world);
+        save.saved_at = save::WallTime(save.saved_at.0 - away.as_millis() as u64);
+        let path = world
+            .query::<&game::Config>()
+            .single(world)
+            .save
+            .path
+            .clone();
+        save::write_toml(&path, &save).unwrap();
+    }
+
+    #[test]
+    fn perks_apply_to_the_time_away() {
+        rust_i18n::set_locale("ru");
+        let config = || {
+            let mut config = config_in_temp_dir("perks_offline");
+            config.offline.simulate_events = false;
+            config
+        };
+        let mut world = setup_world(config(), GameClock::manual(), GameRng::new(0));
+        world
+            .query::<&mut components::BoughtUpgrades>()
+            .single_mut(&mut world)
+            .increment("trade_hall");
+        save_closed_for(&mut world, std::time::Duration::from_secs(100));
+        let mut prestige = components::Prestige::default();
+        prestige.perks.insert("royal_charter".to_string(), 3);
+        let path = world
+            .query::<&game::Config>()
+            .single(&world)
+            .prestige
+            .path
+            .clone();
+        save::write_toml(&path, &prestige).unwrap();
+
+        let mut restored = setup_world(config(), GameClock::manual(), GameRng::new(0));
+        restore_world(&mut restored);
+        remove_temp_dir(&mut restored);
+
+        let (report, bought_upgrades, prestige, config) = restored
+            .query::<(
+                &components::OfflineReport,
+                &components::BoughtUpgrades,
+                &components::Prestige,
+                &game::Config,
+            )>()
+            .single(&restored);
+        let income = game::calculate_gold_per_second(bought_upgrades, config) as f32
+            * prestige::gold_factor(prestige, config);
+        assert!(prestige::gold_factor(prestige, config) > 1.0);
+        assert_eq!(report.0.as_ref().unwrap().gold_gained, income as u64 * 100);
+    }
+
+    #[test]
+    fn the_time_away_adds_to_the_lifetime_statistics_on_load() {
+        rust_i18n::set_locale("ru");
+        let config = || config_in_temp_dir("lifetime_offline");
+        let mut world = setup_world(config(), GameClock::manual(), GameRng::new(0));
+        // Too weak to last the time away
+        world
+            .query::<&mut components::Defense>()
+            .single_mut(&mut world)
+            .0 = 1;
+        save_closed_for(&mut world, std::time::Duration::from_secs(600));
+        let lifetime = components::Totals {
+            clicks: 5,
+            castles_fallen: 2,
+            ..Default::default()
+        };
+        let path = world
+            .query::<&game::Config>()
+            .single(&world)
+            .save
+            .statistics_path
+            .clone();
+        save::write_toml(&path, &lifetime).unwrap();
+
+        let mut restored = setup_world(config(), GameClock::manual(), GameRng::new(0));
+        restore_world(&mut restored);
+        remove_temp_dir(&mut restored);
+
+        let (statistics, report) = restored
+            .query::<(&components::Statistics, &components::OfflineReport)>()
+            .single(&restored);
+        let away = &report.0.as_ref().unwrap().totals;
+        assert_eq!(away.castles_fallen, 1);
+        assert_eq!(statistics.run.castles_fallen, 1);
+        assert_eq!(statistics.lifetime.clicks, 5);
+        assert_eq!(statistics.lifetime.castles_fallen, 3);
+        assert_eq!(
+            statistics.lifetime.gold_from_income + statistics.lifetime.gold_from_events,
+            away.gold_from_income + away.gold_from_events
+        );
+    }
+}
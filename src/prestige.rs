@@ -0,0 +1,174 @@
+use serde::Deserialize;
+use std::{error::Error, time::Duration};
+
+use crate::{
+    components::Prestige,
+    game::{Config, CostCurve},
+};
+
+/// The `[prestige]` section: renown for abandoning a castle and the perks it buys.
+#[derive(Debug, Deserialize)]
+pub struct PrestigeConfig {
+    /// File the renown and perks are kept in between runs.
+    pub path: String,
+    /// Gold a run must earn for the first point of renown; more grows with the square root.
+    pub gold_per_point: f64,
+    /// Seconds the castle has to stand per point of renown.
+    pub seconds_per_point: f32,
+    pub perks: Vec<PerkConfig>,
+}
+
+/// A permanent bonus bought with renown, declared as `[[prestige.perks]]`.
+#[derive(Debug, Deserialize)]
+pub struct PerkConfig {
+    pub id: String,
+    pub icon: String,
+    pub name: String,
+    pub bonus: PerkBonus,
+    /// Bonus per level: a share for gold per second and event damage, gold for clicks.
+    pub amount: f32,
+    /// Price in renown of the next level.
+    pub cost: CostCurve,
+    pub max_level: Option<u32>,
+}
+
+impl PerkConfig {
+    pub fn is_maxed(&self, level: u32) -> bool {
+        self.max_level.is_some_and(|max| level >= max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PerkBonus {
+    /// Multiplies gold per second by `1 + amount * level`.
+    GoldPerSecond,
+    /// Adds `amount * level` gold to every click.
+    ClickValue,
+    /// Takes `amount * level` off the defense damage of events.
+    EventDamage,
+}
+
+fn bonus(prestige: &Prestige, config: &Config, kind: PerkBonus) -> f32 {
+    config
+        .prestige
+        .perks
+        .iter()
+        .filter(|perk| perk.bonus == kind)
+        .map(|perk| perk.amount * prestige.level(&perk.id) as f32)
+        .sum()
+}
+
+pub fn gold_factor(prestige: &Prestige, config: &Config) -> f32 {
+    1.0 + bonus(prestige, config, PerkBonus::GoldPerSecond)
+}
+
+/// Gold mined by a single click.
+pub fn click_value(prestige: &Prestige, config: &Config) -> u64 {
+    1 + bonus(prestige, config, PerkBonus::ClickValue) as u64
+}
+
+/// Share of event damage that still gets through.
+pub fn damage_factor(prestige: &Prestige, config: &Config) -> f32 {
+    (1.0 - bonus(prestige, config, PerkBonus::EventDamage)).max(0.0)
+}
+
+/// Renown for abandoning a castle now: the square root of the gold the run
+/// earned in `gold_per_point`s, plus a point per `seconds_per_point` it stood.
+pub fn renown_for_run(gold_earned: u64, run_time: Duration, config: &Config) -> u64 {
+    let prestige = &config.prestige;
+    let for_gold = (gold_earned as f64 / prestige.gold_per_point).sqrt() as u64;
+    let for_time = (run_time.as_secs_f32() / prestige.seconds_per_point) as u64;
+    for_gold + for_time
+}
+
+/// Buys the next level of `perk`, returning its price, or `None` when the
+/// perk is maxed or the renown does not cover it.
+pub fn buy_perk(prestige: &mut Prestige, perk: &PerkConfig) -> Option<u64> {
+    let level = prestige.level(&perk.id);
+    let cost = perk.cost.price(level);
+    if perk.is_maxed(level) || prestige.renown < cost {
+        return None;
+    }
+
+    prestige.renown -= cost;
+    *prestige.perks.entry(perk.id.clone()).or_default() += 1;
+    Some(cost)
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn Error>> {
+    let prestige = &config.prestige;
+    if prestige.gold_per_point <= 0.0 || prestige.seconds_per_point <= 0.0 {
+        return Err("prestige.gold_per_point and seconds_per_point must be positive".into());
+    }
+
+    for (index, perk) in prestige.perks.iter().enumerate() {
+        if prestige.perks[..index]
+            .iter()
+            .any(|other| other.id == perk.id)
+        {
+            return Err(format!("duplicate perk id {}", perk.id).into());
+        }
+        if let Some(problem) = perk.cost.problem() {
+            return Err(format!("perk {} {problem}", perk.id).into());
+        }
+    }
+
+    let max_reduction: f32 = prestige
+        .perks
+        .iter()
+        .filter(|perk| perk.bonus == PerkBonus::EventDamage)
+        .map(|perk| {
+            perk.max_level
+                .map_or(f32::INFINITY, |max| perk.amount * max as f32)
+        })
+        .sum();
+    if max_reduction >= 1.0 {
+        return Err("event_damage perks must not be able to prevent all damage".into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn perks_cost_renown_and_stop_at_their_max_level() {
+        let config = fixture::config();
+        let perk = config
+            .prestige
+            .perks
+            .iter()
+            .find(|perk| perk.id == "golden_pick")
+            .unwrap();
+        let mut prestige = Prestige {
+            renown: 1_000_000,
+            ..Default::default()
+        };
+
+        let first = buy_perk(&mut prestige, perk);
+        assert_eq!(first, Some(perk.cost.price(0)));
+        while buy_perk(&mut prestige, perk).is_some() {}
+
+        assert_eq!(Some(prestige.level("golden_pick")), perk.max_level);
+        assert_eq!(
+            click_value(&prestige, &config),
+            1 + (perk.amount * prestige.level("golden_pick") as f32) as u64
+        );
+    }
+
+    #[test]
+    fn renown_grows_with_earnings_and_survival() {
+        let config = fixture::config();
+        let prestige = &config.prestige;
+
+        assert_eq!(renown_for_run(0, Duration::ZERO, &config), 0);
+        let gold = (prestige.gold_per_point * 9.0) as u64;
+        assert_eq!(renown_for_run(gold, Duration::ZERO, &config), 3);
+        let run_time = Duration::from_secs_f32(prestige.seconds_per_point * 2.0);
+        assert_eq!(renown_for_run(gold, run_time, &config), 5);
+    }
+}
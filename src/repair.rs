@@ -0,0 +1,71 @@
+use serde::Deserialize;
+
+use crate::{
+    components::{BoughtUpgrades, RepairJob, RepairQueue},
+    game::Config,
+};
+
+/// The `[repair]` section: paying gold to mend the walls over time.
+#[derive(Debug, Deserialize)]
+pub struct RepairConfig {
+    /// Gold per point of defense repaired.
+    pub cost_per_point: f32,
+    /// Points mended per second before upgrades.
+    pub speed: u64,
+    /// Points ordered with a single press.
+    pub batch: u64,
+}
+
+/// Points mended per second, including upgrades that speed repairs up.
+pub fn repair_speed(bought_upgrades: &BoughtUpgrades, config: &Config) -> u64 {
+    config.repair.speed + config.effects.repair_speed(bought_upgrades)
+}
+
+/// Points the next order would repair: a batch, or whatever damage is left
+/// once the walls and the queued repairs are counted.
+pub fn order_size(defense: u64, max_defense: u64, queue: &RepairQueue, config: &Config) -> u64 {
+    let damage = max_defense.saturating_sub(defense + queue.pending());
+    damage.min(config.repair.batch)
+}
+
+pub fn order_cost(points: u64, config: &Config) -> u64 {
+    (points as f32 * config.repair.cost_per_point).ceil() as u64
+}
+
+pub fn order(queue: &mut RepairQueue, points: u64) {
+    queue.0.push_back(RepairJob { points, done: 0 });
+}
+
+/// Mends up to `speed` points from the front of the queue. Work pauses while
+/// the walls are whole, so the rest of a job waits for the next breach.
+pub fn work(queue: &mut RepairQueue, defense: &mut u64, max_defense: u64, speed: u64) -> u64 {
+    let mut repaired = 0;
+    while repaired < speed && *defense < max_defense {
+        let Some(job) = queue.0.front_mut() else {
+            break;
+        };
+        let step = (speed - repaired)
+            .min(job.points - job.done)
+            .min(max_defense - *defense);
+        job.done += step;
+        *defense += step;
+        repaired += step;
+        if job.done == job.points {
+            queue.0.pop_front();
+        }
+    }
+    repaired
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if config.repair.cost_per_point < 0.0 {
+        return Err("repair.cost_per_point must not be negative".into());
+    }
+    if config.repair.speed == 0 {
+        return Err("repair.speed must be positive".into());
+    }
+    if config.repair.batch == 0 {
+        return Err("repair.batch must be positive".into());
+    }
+    Ok(())
+}
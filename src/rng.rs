@@ -0,0 +1,134 @@
+use bevy_ecs::prelude::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The only source of randomness in a run, so a seed reproduces it exactly.
+#[derive(Resource, Debug)]
+pub struct GameRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+/// Position of a [`GameRng`] in its stream, as stored in a save file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RngState {
+    #[serde(with = "seed_format")]
+    pub seed: u64,
+    pub word_pos: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Random seeds stay within `i64::MAX`, so they can be put back into `rng.seed`.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random::<u64>() >> 1)
+    }
+
+    /// A fresh stream from the configured seed, or a random one when unset.
+    pub fn for_new_run(seed: Option<u64>) -> Self {
+        seed.map_or_else(Self::from_entropy, Self::new)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            word_pos: self.rng.get_word_pos() as u64,
+        }
+    }
+
+    pub fn from_state(state: RngState) -> Self {
+        let mut rng = Self::new(state.seed);
+        rng.rng.set_word_pos(state.word_pos as u128);
+        rng
+    }
+}
+
+/// TOML integers are `i64`, so seeds past `i64::MAX`, such as one given with
+/// `--seed`, are written as strings instead.
+pub mod seed_format {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        match i64::try_from(*seed) {
+            Ok(seed) => serializer.serialize_i64(seed),
+            Err(_) => serializer.serialize_str(&seed.to_string()),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Seed {
+            Number(u64),
+            Text(String),
+        }
+
+        match Seed::deserialize(deserializer)? {
+            Seed::Number(seed) => Ok(seed),
+            Seed::Text(seed) => seed.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.rng.fill_bytes(dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(rng: &mut GameRng, count: usize) -> Vec<u64> {
+        (0..count).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_sequence() {
+        let (mut first, mut second) = (GameRng::new(42), GameRng::new(42));
+        assert_eq!(draw(&mut first, 16), draw(&mut second, 16));
+        assert_ne!(
+            draw(&mut GameRng::new(43), 16),
+            draw(&mut GameRng::new(42), 16)
+        );
+    }
+
+    #[test]
+    fn random_seeds_fit_into_the_config() {
+        for _ in 0..64 {
+            assert!(i64::try_from(GameRng::from_entropy().seed()).is_ok());
+        }
+    }
+
+    #[test]
+    fn a_restored_stream_continues_where_it_was_saved() {
+        let mut rng = GameRng::new(7);
+        draw(&mut rng, 5);
+        let saved = toml::to_string(&rng.state()).unwrap();
+
+        let mut restored = GameRng::from_state(toml::from_str(&saved).unwrap());
+
+        assert_eq!(restored.seed(), 7);
+        assert_eq!(draw(&mut restored, 16), draw(&mut rng, 16));
+    }
+}
@@ -0,0 +1,552 @@
+use bevy_ecs::prelude::*;
+use rust_i18n::t;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    io::ErrorKind,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    clock::GameClock,
+    components::{
+        BoughtUpgrades, Defense, EventMessage, FatalBlow, GameRunning, GameState, Gold, GoldEarned,
+        LastClick, LastEventCheck, LastSave, LastTick, MaxDefense, OfflineReport, Prestige,
+        RepairQueue, RunStarted, SelectedUpgrade, Statistics, Totals, Wave,
+    },
+    game::{self, Config, OfflineStart},
+    prestige,
+    rng::{GameRng, RngState},
+};
+
+/// Bump whenever the layout of [`SaveGame`] changes incompatibly.
+pub const SAVE_VERSION: u32 = 3;
+
+/// Version 1 keyed upgrades by enum variant instead of their config id.
+const V1_UPGRADE_IDS: [(&str, &str); 7] = [
+    ("Catapult", "catapult"),
+    ("Archer", "archer"),
+    ("Warrior", "warrior"),
+    ("Officer", "officer"),
+    ("OilReserve", "oil"),
+    ("TradeHall", "trade_hall"),
+    ("MidasHand", "midas_hand"),
+];
+
+/// Milliseconds since the Unix epoch.
+///
+/// `Instant`s only make sense within a single process, so timers are stored
+/// as wall-clock timestamps and mapped back onto the monotonic clock on load.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WallTime(pub u64);
+
+impl WallTime {
+    pub fn now() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self(since_epoch.as_millis() as u64)
+    }
+
+    pub fn from_instant(instant: Instant, now: Instant) -> Self {
+        let age = now.saturating_duration_since(instant);
+        Self(Self::now().0.saturating_sub(age.as_millis() as u64))
+    }
+
+    pub fn to_instant(self, now: Instant) -> Instant {
+        now.checked_sub(self.elapsed()).unwrap_or(now)
+    }
+
+    pub fn elapsed(self) -> Duration {
+        Duration::from_millis(Self::now().0.saturating_sub(self.0))
+    }
+
+    /// The UTC calendar date as `YYYY-MM-DD`.
+    pub fn date(self) -> String {
+        // Howard Hinnant's days-to-civil, with years starting in March
+        let days = (self.0 / 86_400_000) as i64 + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub saved_at: WallTime,
+    pub gold: u64,
+    #[serde(default)]
+    pub gold_earned: u64,
+    pub defense: u64,
+    pub max_defense: u64,
+    #[serde(default)]
+    pub repairs: RepairQueue,
+    /// Totals of this run; the lifetime ones live in their own file.
+    #[serde(default)]
+    pub statistics: Totals,
+    pub selected_upgrade: String,
+    pub bought_upgrades: HashMap<String, u32>,
+    pub last_tick: WallTime,
+    pub last_event_check: WallTime,
+    pub last_click: WallTime,
+    /// Missing in saves made before events could depend on the run time.
+    #[serde(default)]
+    pub run_started: Option<WallTime>,
+    #[serde(default)]
+    pub wave: Option<SavedWave>,
+    #[serde(default)]
+    pub rng: Option<RngState>,
+}
+
+/// The upcoming wave, timed relative to `saved_at`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedWave {
+    pub number: u32,
+    pub until_next: f32,
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+impl SaveGame {
+    pub fn from_world(world: &mut World) -> Self {
+        let now = world.resource::<GameClock>().now();
+        let mut query = world.query::<(
+            (&Gold, &GoldEarned),
+            (&Defense, &MaxDefense, &RepairQueue, &Statistics),
+            &SelectedUpgrade,
+            &BoughtUpgrades,
+            &LastTick,
+            &LastEventCheck,
+            &LastClick,
+            &RunStarted,
+            &Wave,
+        )>();
+        let (
+            (gold, gold_earned),
+            (defense, max_defense, repairs, statistics),
+            selected_upgrade,
+            bought_upgrades,
+            last_tick,
+            last_event_check,
+            last_click,
+            run_started,
+            wave,
+        ) = query.single(world);
+
+        Self {
+            version: SAVE_VERSION,
+            saved_at: WallTime::now(),
+            gold: gold.0,
+            gold_earned: gold_earned.0,
+            defense: defense.0,
+            max_defense: max_defense.0,
+            repairs: repairs.clone(),
+            statistics: statistics.run.clone(),
+            selected_upgrade: selected_upgrade.0.clone(),
+            bought_upgrades: bought_upgrades.0.clone(),
+            last_tick: WallTime::from_instant(last_tick.0, now),
+            last_event_check: WallTime::from_instant(last_event_check.0, now),
+            last_click: WallTime::from_instant(last_click.0, now),
+            run_started: Some(WallTime::from_instant(run_started.0, now)),
+            wave: Some(SavedWave {
+                number: wave.number,
+                until_next: wave.next_at.saturating_duration_since(now).as_secs_f32(),
+            }),
+            rng: Some(world.resource::<GameRng>().state()),
+        }
+    }
+
+    /// Restores the saved entity and credits the time the game was closed.
+    pub fn apply(self, world: &mut World) {
+        // A resumed run continues its own random sequence
+        if let Some(state) = self.rng {
+            world.insert_resource(GameRng::from_state(state));
+        }
+        world.resource_scope(|world, mut rng: Mut<GameRng>| self.restore(world, &mut rng));
+    }
+
+    fn restore(self, world: &mut World, rng: &mut GameRng) {
+        let now = world.resource::<GameClock>().now();
+        let mut query = world.query::<(
+            (&mut Gold, &mut GoldEarned, &mut Defense, &mut MaxDefense),
+            (&mut SelectedUpgrade, &mut BoughtUpgrades, &mut RepairQueue),
+            (
+                &mut LastTick,
+                &mut LastEventCheck,
+                &mut LastClick,
+                &mut RunStarted,
+            ),
+            (&mut EventMessage, &mut OfflineReport, &mut FatalBlow),
+            (&mut GameRunning, &mut GameState, &mut Wave, &mut Statistics),
+            (&Prestige, &Config),
+        )>();
+        let (
+            (mut gold, mut gold_earned, mut defense, mut max_defense),
+            (mut selected_upgrade, mut bought_upgrades, mut repairs),
+            (mut last_tick, mut last_event_check, mut last_click, mut run_started),
+            (mut event_message, mut offline_report, mut fatal_blow),
+            (mut game_running, mut game_state, mut wave, mut statistics),
+            (prestige, config),
+        ) = query.single_mut(world);
+
+        // Keep the default selection if the upgrade has left the catalog
+        if config.upgrade(&self.selected_upgrade).is_some() {
+            *selected_upgrade = SelectedUpgrade(self.selected_upgrade);
+        }
+        *bought_upgrades = BoughtUpgrades(self.bought_upgrades);
+        *repairs = self.repairs;
+        *last_click = LastClick(self.last_click.to_instant(now));
+
+        let run_started_at = self.run_started.unwrap_or(self.saved_at);
+        let played = Duration::from_millis(self.saved_at.0.saturating_sub(run_started_at.0));
+        let saved_wave = self.wave.unwrap_or(SavedWave {
+            number: 1,
+            until_next: config.waves.first_wave,
+        });
+        let start = OfflineStart {
+            gold: self.gold,
+            defense: self.defense,
+            gold_factor: prestige::gold_factor(prestige, config),
+            damage_factor: prestige::damage_factor(prestige, config),
+            run_time: played,
+            wave: saved_wave.number,
+            until_wave: Duration::from_secs_f32(saved_wave.until_next),
+        };
+        let progress = game::calculate_offline_progress(
+            self.saved_at.elapsed(),
+            start,
+            &mut bought_upgrades,
+            &mut repairs,
+            config,
+            rng,
+        );
+
+        // Only the credited part of the time away counts towards the run
+        *run_started = RunStarted(now.checked_sub(played + progress.credited).unwrap_or(now));
+
+        // Timers resume as if the credited time had been played
+        let catch_up =
+            |wall_time: WallTime| (wall_time.to_instant(now) + progress.credited).min(now);
+        *last_tick = LastTick(catch_up(self.last_tick));
+        *last_event_check = LastEventCheck(catch_up(self.last_event_check));
+        *wave = Wave {
+            number: progress.next_wave,
+            next_at: now + progress.until_wave,
+        };
+
+        *gold = Gold((self.gold + progress.gold_gained).saturating_sub(progress.gold_lost));
+        *gold_earned = GoldEarned(self.gold_earned + progress.gold_gained);
+        // Units destroyed while away may have taken wall capacity with them
+        *max_defense = MaxDefense(game::calculate_max_defense(&bought_upgrades, config));
+        *defense = Defense(
+            (self.defense + progress.defense_gained)
+                .saturating_sub(progress.defense_lost)
+                .min(max_defense.0),
+        );
+
+        statistics.run = self.statistics;
+        statistics.record(|totals| totals.add(&progress.totals));
+
+        if progress.castle_fell {
+            defense.0 = 0;
+            event_message.message = t!("game.offline.castle_fell").to_string();
+            fatal_blow.0 = Some(event_message.message.clone());
+            event_message.timestamp = now;
+            game_running.0 = false;
+            *game_state = GameState::GameOver;
+        }
+
+        if progress.credited >= Duration::from_secs(1) {
+            offline_report.0 = Some(progress);
+        }
+    }
+}
+
+/// Reads the save file, returning `Ok(None)` when there is nothing to restore.
+pub fn load(path: &str) -> Result<Option<SaveGame>, Box<dyn Error>> {
+    let save_str = match fs::read_to_string(path) {
+        Ok(save_str) => save_str,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let header: SaveHeader = toml::from_str(&save_str)?;
+    match header.version {
+        SAVE_VERSION => Ok(Some(toml::from_str(&save_str)?)),
+        version @ (1 | 2) => {
+            let mut save: toml::Table = toml::from_str(&save_str)?;
+            if version == 1 {
+                migrate_v1(&mut save);
+            }
+            migrate_v2(&mut save);
+            Ok(Some(save.try_into()?))
+        }
+        version => Err(format!("unsupported save version {version}").into()),
+    }
+}
+
+fn migrate_v1(save: &mut toml::Table) {
+    let rename = |old: &str| {
+        V1_UPGRADE_IDS
+            .iter()
+            .find(|(variant, _)| *variant == old)
+            .map_or(old, |(_, id)| id)
+            .to_string()
+    };
+
+    if let Some(toml::Value::String(selected)) = save.get_mut("selected_upgrade") {
+        *selected = rename(selected);
+    }
+    if let Some(toml::Value::Table(bought)) = save.remove("bought_upgrades") {
+        let bought = bought
+            .into_iter()
+            .map(|(variant, count)| (rename(&variant), count))
+            .collect();
+        save.insert("bought_upgrades".into(), toml::Value::Table(bought));
+    }
+    save.insert("version".into(), 2.into());
+}
+
+/// Version 2 kept only the count of events survived, outside the run statistics.
+fn migrate_v2(save: &mut toml::Table) {
+    if let Some(events_survived) = save.remove("events_survived") {
+        let mut statistics = toml::Table::new();
+        statistics.insert("events_survived".into(), events_survived);
+        save.insert("statistics".into(), toml::Value::Table(statistics));
+    }
+    save.insert("version".into(), SAVE_VERSION.into());
+}
+
+/// Reads a TOML file kept across runs, returning `Ok(None)` when it does not exist yet.
+pub fn load_toml<T: DeserializeOwned>(path: &str) -> Result<Option<T>, Box<dyn Error>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(toml::from_str(&contents)?)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes next to the target first so a crash never leaves a truncated file.
+pub fn write_toml<T: Serialize>(path: &str, value: &T) -> Result<(), Box<dyn Error>> {
+    let tmp_path = format!("{path}.tmp");
+    fs::write(&tmp_path, toml::to_string(value)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub fn save_world(world: &mut World) -> Result<(), Box<dyn Error>> {
+    let now = world.resource::<GameClock>().now();
+    let path = world.query::<&Config>().single(world).save.path.clone();
+    let save = SaveGame::from_world(world);
+    write_toml(&path, &save)?;
+    save_statistics(world)?;
+
+    let mut query = world.query::<&mut LastSave>();
+    query.single_mut(world).0 = now;
+
+    Ok(())
+}
+
+/// Writes the lifetime statistics; the run's own go into the save.
+pub fn save_statistics(world: &mut World) -> Result<(), Box<dyn Error>> {
+    let mut query = world.query::<(&Statistics, &Config)>();
+    let (statistics, config) = query.single(world);
+    write_toml(&config.save.statistics_path, &statistics.lifetime)
+}
+
+/// Removes the save so a fallen castle is not restored on the next launch.
+pub fn discard_world(world: &mut World) -> Result<(), Box<dyn Error>> {
+    let path = &world.query::<&Config>().single(world).save.path;
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn autosave_system(world: &mut World) {
+    let now = world.resource::<GameClock>().now();
+    let mut query = world.query::<(&GameState, &LastSave, &Config)>();
+    let (game_state, last_save, config) = query.single(world);
+
+    let interval = Duration::from_secs_f32(config.save.autosave_interval);
+    if !game_state.in_run() || now.duration_since(last_save.0) < interval {
+        return;
+    }
+
+    if save_world(world).is_err() {
+        let mut query = world.query::<(&mut EventMessage, &mut LastSave)>();
+        let (mut event_message, mut last_save) = query.single_mut(world);
+        event_message.message = t!("game.save.failed").to_string();
+        event_message.timestamp = now;
+        // Retry on the next interval instead of every frame
+        last_save.0 = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn setup() -> World {
+        crate::setup_world(fixture::config(), GameClock::manual(), GameRng::new(0))
+    }
+
+    /// A save file of its own for every test, since they run in parallel.
+    fn save_path(test: &str) -> String {
+        let file = format!("castle-clickers-{}-{test}.toml", std::process::id());
+        std::env::temp_dir()
+            .join(file)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Writes `contents` as a save file and loads it back.
+    fn load_from(test: &str, contents: &str) -> SaveGame {
+        let path = save_path(test);
+        fs::write(&path, contents).unwrap();
+        let save = load(&path);
+        fs::remove_file(&path).unwrap();
+        save.unwrap().expect("the save was just written")
+    }
+
+    #[test]
+    fn a_saved_castle_loads_back_as_it_was() {
+        let mut world = setup();
+        let (mut gold, mut defense, mut selected, mut bought_upgrades, mut statistics) = world
+            .query::<(
+                &mut Gold,
+                &mut Defense,
+                &mut SelectedUpgrade,
+                &mut BoughtUpgrades,
+                &mut Statistics,
+            )>()
+            .single_mut(&mut world);
+        gold.0 = 1234;
+        defense.0 = 90;
+        selected.0 = "archer".to_string();
+        bought_upgrades.increment("archer");
+        bought_upgrades.increment("archer");
+        statistics.run.clicks = 3;
+        rand::RngCore::next_u64(&mut *world.resource_mut::<GameRng>());
+
+        let save = SaveGame::from_world(&mut world);
+        let save = load_from("round_trip", &toml::to_string(&save).unwrap());
+        let mut restored = setup();
+        save.apply(&mut restored);
+
+        let (gold, defense, selected, bought_upgrades, statistics) = restored
+            .query::<(
+                &Gold,
+                &Defense,
+                &SelectedUpgrade,
+                &BoughtUpgrades,
+                &Statistics,
+            )>()
+            .single(&restored);
+        assert_eq!((gold.0, defense.0), (1234, 90));
+        assert_eq!(selected.0, "archer");
+        assert_eq!(bought_upgrades.get_count("archer"), 2);
+        assert_eq!(statistics.run.clicks, 3);
+        let (saved_rng, restored_rng) = (
+            world.resource::<GameRng>().state(),
+            restored.resource::<GameRng>().state(),
+        );
+        assert_eq!(
+            (saved_rng.seed, saved_rng.word_pos),
+            (restored_rng.seed, restored_rng.word_pos)
+        );
+    }
+
+    #[test]
+    fn seeds_past_the_range_of_toml_integers_are_saved() {
+        let mut world = setup();
+        let seed = u64::MAX - 5;
+        world.insert_resource(GameRng::new(seed));
+
+        let save = SaveGame::from_world(&mut world);
+        let save = load_from("large_seed", &toml::to_string(&save).unwrap());
+        let mut restored = setup();
+        save.apply(&mut restored);
+
+        assert_eq!(restored.resource::<GameRng>().seed(), seed);
+    }
+
+    #[test]
+    fn only_credited_time_away_counts_towards_the_run() {
+        let mut world = setup();
+        world
+            .resource_mut::<GameClock>()
+            .advance(Duration::from_secs(50));
+        let mut save = SaveGame::from_world(&mut world);
+        let week = 7 * 24 * 3_600_000;
+        save.saved_at.0 -= week;
+        save.run_started = save.run_started.map(|started| WallTime(started.0 - week));
+
+        let mut restored = setup();
+        save.apply(&mut restored);
+
+        let now = restored.resource::<GameClock>().now();
+        let (run_started, config) = restored.query::<(&RunStarted, &Config)>().single(&restored);
+        let run_time = now.duration_since(run_started.0);
+        let expected =
+            Duration::from_secs(50) + Duration::from_secs_f32(config.offline.max_duration);
+        assert!(run_time.abs_diff(expected) < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn version_1_saves_are_migrated_to_upgrade_ids() {
+        let save = load_from(
+            "version_1",
+            r#"
+            version = 1
+            saved_at = 0
+            gold = 500
+            defense = 70
+            max_defense = 120
+            selected_upgrade = "TradeHall"
+            last_tick = 0
+            last_event_check = 0
+            last_click = 0
+
+            [bought_upgrades]
+            Archer = 3
+            OilReserve = 1
+            "#,
+        );
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!((save.gold, save.defense), (500, 70));
+        assert_eq!(save.selected_upgrade, "trade_hall");
+        let mut bought: Vec<_> = save.bought_upgrades.into_iter().collect();
+        bought.sort();
+        assert_eq!(bought, [("archer".to_string(), 3), ("oil".to_string(), 1)]);
+        assert_eq!(save.statistics.events_survived, 0);
+    }
+
+    #[test]
+    fn wall_times_read_as_utc_dates() {
+        assert_eq!(WallTime(0).date(), "1970-01-01");
+        assert_eq!(WallTime(1_709_164_800_000).date(), "2024-02-29");
+        assert_eq!(WallTime(1_735_689_599_000).date(), "2024-12-31");
+    }
+}
@@ -0,0 +1,172 @@
+use bevy_ecs::prelude::*;
+use serde::Deserialize;
+use std::{error::Error, path::Path};
+
+use crate::{
+    components::{Leaderboard, LeaderboardEntry, RunRecord},
+    game::Config,
+    save,
+};
+
+/// The `[score]` section: points a fallen castle scores and the leaderboard it is ranked on.
+#[derive(Debug, Deserialize)]
+pub struct ScoreConfig {
+    /// Points per second the castle stood.
+    pub per_second: f64,
+    /// Points per gold earned over the run.
+    pub per_gold: f64,
+    /// Points per event the castle came through.
+    pub per_event: f64,
+    /// Points per wave repelled.
+    pub per_wave: f64,
+    /// Leaderboard file name, kept next to the save.
+    pub leaderboard: String,
+    /// Entries kept for each config hash.
+    pub leaderboard_size: usize,
+}
+
+pub fn score(run: &RunRecord, config: &Config) -> u64 {
+    let weights = &config.score;
+    (run.survived as f64 * weights.per_second
+        + run.gold_earned as f64 * weights.per_gold
+        + run.events_survived as f64 * weights.per_event
+        + run.waves_repelled as f64 * weights.per_wave) as u64
+}
+
+pub fn leaderboard_path(config: &Config) -> String {
+    Path::new(&config.save.path)
+        .with_file_name(&config.score.leaderboard)
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn save_leaderboard(world: &mut World) -> Result<(), Box<dyn Error>> {
+    let (leaderboard, config) = world.query::<(&Leaderboard, &Config)>().single(world);
+    save::write_toml(&leaderboard_path(config), leaderboard)
+}
+
+/// FNV-1a of the settings with comments and layout stripped, so that runs
+/// under different balance are never ranked against each other.
+pub fn config_hash(config_str: &str) -> Result<String, Box<dyn Error>> {
+    let settings: toml::Value = toml::from_str(config_str)?;
+    let hash = toml::to_string(&settings)?
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    Ok(format!("{hash:016x}"))
+}
+
+/// Ranks `run` under the current config, keeping the best `leaderboard_size`
+/// entries of its hash; ties go to the earlier run.
+pub fn submit(leaderboard: &mut Leaderboard, run: RunRecord, config: &Config) {
+    let at = leaderboard
+        .entries
+        .partition_point(|entry| entry.run.score >= run.score);
+    leaderboard.entries.insert(
+        at,
+        LeaderboardEntry {
+            config_hash: config.hash.clone(),
+            run,
+        },
+    );
+
+    let mut kept = 0;
+    leaderboard.entries.retain(|entry| {
+        if entry.config_hash != config.hash {
+            return true;
+        }
+        kept += 1;
+        kept <= config.score.leaderboard_size
+    });
+}
+
+/// Entries played under the current config, best first.
+pub fn ranked<'a>(leaderboard: &'a Leaderboard, config: &Config) -> Vec<&'a LeaderboardEntry> {
+    leaderboard
+        .entries
+        .iter()
+        .filter(|entry| entry.config_hash == config.hash)
+        .collect()
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn Error>> {
+    let weights = &config.score;
+    if [
+        weights.per_second,
+        weights.per_gold,
+        weights.per_event,
+        weights.per_wave,
+    ]
+    .iter()
+    .any(|weight| *weight < 0.0)
+    {
+        return Err("score weights must not be negative".into());
+    }
+    if config.score.leaderboard_size == 0 {
+        return Err("score.leaderboard_size must be at least 1".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn scores_follow_the_configured_weights() {
+        let config = fixture::config();
+        let run = RunRecord {
+            survived: 120.0,
+            gold_earned: 5000,
+            events_survived: 7,
+            waves_repelled: 3,
+            ..fixture::finished_run(0, 0)
+        };
+
+        let weights = &config.score;
+        let expected = 120.0 * weights.per_second
+            + 5000.0 * weights.per_gold
+            + 7.0 * weights.per_event
+            + 3.0 * weights.per_wave;
+        assert_eq!(score(&run, &config), expected as u64);
+    }
+
+    #[test]
+    fn the_leaderboard_ranks_runs_only_against_the_same_config() {
+        let config = fixture::config();
+        let size = config.score.leaderboard_size;
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.entries.push(LeaderboardEntry {
+            config_hash: "other balance".to_string(),
+            run: fixture::finished_run(0, 1_000_000),
+        });
+        for seed in 0..size as u64 + 2 {
+            submit(
+                &mut leaderboard,
+                fixture::finished_run(seed, seed % 3 * 10),
+                &config,
+            );
+        }
+
+        let ranked = ranked(&leaderboard, &config);
+        assert_eq!(ranked.len(), size);
+        assert!(ranked
+            .windows(2)
+            .all(|pair| pair[0].run.score >= pair[1].run.score));
+        assert_eq!(leaderboard.entries.len(), size + 1);
+        assert_eq!(leaderboard.entries[0].config_hash, "other balance");
+        // Of the runs tied on the best score, the first one played ranks first
+        assert_eq!((ranked[0].run.score, ranked[0].run.seed), (20, 2));
+    }
+
+    #[test]
+    fn the_config_hash_ignores_comments_but_not_settings() {
+        let hash = |settings: &str| config_hash(settings).unwrap();
+        let base = hash("[click]\ncooldown = 1.0\n");
+
+        assert_eq!(hash("# Clicking\n[click]\ncooldown   = 1.0\n"), base);
+        assert_ne!(hash("[click]\ncooldown = 0.5\n"), base);
+    }
+}
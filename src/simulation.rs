@@ -0,0 +1,319 @@
+use bevy_ecs::prelude::*;
+use serde::Serialize;
+use std::{collections::BTreeMap, error::Error, time::Duration};
+
+use crate::{
+    clock::GameClock,
+    components::{
+        BoughtUpgrades, Defense, GameState, Gold, GoldEarned, RunStarted, SelectedUpgrade, Upgrades,
+    },
+    game::{self, Config, UnlockProgress, UpgradeConfig},
+    input,
+    rng::GameRng,
+};
+
+/// How the simulated player spends gold.
+#[derive(Debug, Clone)]
+pub enum Strategy {
+    /// Always buys the cheapest upgrade it can afford.
+    Cheapest,
+    /// Buys whatever adds the most defense per gold.
+    Defensive,
+    /// Buys the listed upgrades in order, waiting until each is affordable.
+    Script(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug)]
+pub struct SimulationOptions {
+    pub seeds: u64,
+    pub first_seed: u64,
+    pub duration: Duration,
+    pub step: Duration,
+    pub sample_interval: Duration,
+    pub clicks: bool,
+    pub strategy: Strategy,
+    pub format: ReportFormat,
+    pub curves: bool,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            seeds: 10,
+            first_seed: 0,
+            duration: Duration::from_secs(3600),
+            step: Duration::from_millis(100),
+            sample_interval: Duration::from_secs(10),
+            clicks: true,
+            strategy: Strategy::Cheapest,
+            format: ReportFormat::Csv,
+            curves: false,
+        }
+    }
+}
+
+impl SimulationOptions {
+    /// Parses the arguments following `simulate`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} requires a value"));
+            match arg.as_str() {
+                "--seeds" => options.seeds = value()?.parse()?,
+                "--first-seed" => options.first_seed = value()?.parse()?,
+                "--duration" => options.duration = Duration::try_from_secs_f32(value()?.parse()?)?,
+                "--step" => options.step = Duration::from_millis(value()?.parse()?),
+                "--sample" => {
+                    options.sample_interval = Duration::try_from_secs_f32(value()?.parse()?)?
+                }
+                "--no-clicks" => options.clicks = false,
+                "--strategy" => options.strategy = parse_strategy(&value()?)?,
+                "--format" => {
+                    options.format = match value()?.as_str() {
+                        "csv" => ReportFormat::Csv,
+                        "json" => ReportFormat::Json,
+                        other => return Err(format!("unknown format {other}").into()),
+                    }
+                }
+                "--curves" => options.curves = true,
+                other => return Err(format!("unknown argument {other}").into()),
+            }
+        }
+
+        if options.step.is_zero() {
+            return Err("--step must be positive".into());
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_strategy(value: &str) -> Result<Strategy, Box<dyn Error>> {
+    match value {
+        "cheapest" => Ok(Strategy::Cheapest),
+        "defensive" => Ok(Strategy::Defensive),
+        _ => {
+            let script = value
+                .strip_prefix("script:")
+                .ok_or(format!("unknown strategy {value}"))?;
+            let upgrades = script.split(',').map(|id| id.trim().to_string());
+            Ok(Strategy::Script(upgrades.collect()))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Sample {
+    pub time: f32,
+    pub gold: u64,
+    pub defense: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub seed: u64,
+    pub survival_time: f32,
+    pub game_over: bool,
+    pub gold: u64,
+    pub defense: u64,
+    pub upgrades: BTreeMap<String, u32>,
+    pub curve: Vec<Sample>,
+}
+
+/// Entry point of `castle-clickers simulate`.
+pub fn run(args: impl IntoIterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let options = SimulationOptions::parse(args)?;
+
+    if let Strategy::Script(script) = &options.strategy {
+        let config = Config::load()?;
+        if let Some(id) = script.iter().find(|id| config.upgrade(id).is_none()) {
+            return Err(format!("unknown upgrade {id}").into());
+        }
+    }
+
+    let reports = (options.first_seed..options.first_seed + options.seeds)
+        .map(|seed| Ok(simulate(Config::load()?, seed, &options)))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    match options.format {
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&reports)?),
+        ReportFormat::Csv if options.curves => print_curves_csv(&reports),
+        ReportFormat::Csv => print_summary_csv(&reports),
+    }
+
+    Ok(())
+}
+
+/// Plays one run on a manual clock until the castle falls or time runs out.
+pub fn simulate(config: Config, seed: u64, options: &SimulationOptions) -> RunReport {
+    let mut world = crate::setup_world(config, GameClock::manual(), GameRng::new(seed));
+    let (mut schedule, mut upgrade_schedule) = crate::setup_schedules();
+
+    let mut elapsed = Duration::ZERO;
+    let mut next_sample = Duration::ZERO;
+    let mut script_position = 0;
+    let mut curve = Vec::new();
+
+    loop {
+        if elapsed >= next_sample {
+            let (gold, defense) = world.query::<(&Gold, &Defense)>().single(&world);
+            curve.push(Sample {
+                time: elapsed.as_secs_f32(),
+                gold: gold.0,
+                defense: defense.0,
+            });
+            next_sample += options.sample_interval;
+        }
+
+        let game_state = *world.query::<&GameState>().single(&world);
+        if game_state != GameState::Playing || elapsed >= options.duration {
+            break;
+        }
+
+        world.resource_mut::<GameClock>().advance(options.step);
+        elapsed += options.step;
+        schedule.run(&mut world);
+
+        if options.clicks {
+            input::handle_click(&mut world);
+        }
+        // Only unlocked, affordable upgrades are chosen, so the purchase always goes through
+        if let Some(upgrade) = choose_upgrade(&mut world, &options.strategy, script_position) {
+            world
+                .query::<&mut SelectedUpgrade>()
+                .single_mut(&mut world)
+                .0 = upgrade;
+            upgrade_schedule.run(&mut world);
+            script_position += 1;
+        }
+    }
+
+    let (gold, defense, game_state, bought_upgrades, upgrades) = world
+        .query::<(&Gold, &Defense, &GameState, &BoughtUpgrades, &Upgrades)>()
+        .single(&world);
+
+    RunReport {
+        seed,
+        survival_time: elapsed.as_secs_f32(),
+        game_over: *game_state == GameState::GameOver,
+        gold: gold.0,
+        defense: defense.0,
+        upgrades: upgrades
+            .0
+            .iter()
+            .map(|upgrade| (upgrade.clone(), bought_upgrades.get_count(upgrade)))
+            .collect(),
+        curve,
+    }
+}
+
+fn choose_upgrade(
+    world: &mut World,
+    strategy: &Strategy,
+    script_position: usize,
+) -> Option<String> {
+    let now = world.resource::<GameClock>().now();
+    let (gold, gold_earned, run_started, bought_upgrades, config) = world
+        .query::<(&Gold, &GoldEarned, &RunStarted, &BoughtUpgrades, &Config)>()
+        .single(world);
+    let progress = UnlockProgress {
+        bought_upgrades,
+        gold_earned: gold_earned.0,
+        run_time: now.duration_since(run_started.0),
+    };
+    let purchasable = |upgrade: &&UpgradeConfig| {
+        let level = bought_upgrades.get_count(&upgrade.id);
+        !upgrade.is_maxed(level)
+            && game::missing_requirement(upgrade, &progress).is_none()
+            && game::can_afford_upgrade(gold.0, upgrade.cost(level))
+    };
+    let cost = |upgrade: &&UpgradeConfig| upgrade.cost(bought_upgrades.get_count(&upgrade.id));
+    let affordable = config.upgrades.iter().filter(purchasable);
+
+    let upgrade = match strategy {
+        Strategy::Cheapest => affordable.min_by_key(cost),
+        Strategy::Defensive => affordable
+            .filter(|upgrade| upgrade_value(upgrade, cost(upgrade)) > 0.0)
+            .max_by(|a, b| upgrade_value(a, cost(a)).total_cmp(&upgrade_value(b, cost(b)))),
+        Strategy::Script(script) => script
+            .get(script_position)
+            .and_then(|id| config.upgrade(id))
+            .filter(purchasable),
+    };
+    upgrade.map(|upgrade| upgrade.id.clone())
+}
+
+/// Defense and wall capacity bought per gold.
+fn upgrade_value(upgrade: &UpgradeConfig, cost: u64) -> f32 {
+    (upgrade.defense + upgrade.max_defense) as f32 / cost.max(1) as f32
+}
+
+fn print_summary_csv(reports: &[RunReport]) {
+    let Some(first) = reports.first() else {
+        return;
+    };
+
+    let upgrade_columns: Vec<&str> = first.upgrades.keys().map(String::as_str).collect();
+    println!(
+        "seed,survival_time,game_over,gold,defense,{}",
+        upgrade_columns.join(",")
+    );
+    for report in reports {
+        let counts: Vec<String> = report.upgrades.values().map(u32::to_string).collect();
+        println!(
+            "{},{:.1},{},{},{},{}",
+            report.seed,
+            report.survival_time,
+            report.game_over,
+            report.gold,
+            report.defense,
+            counts.join(",")
+        );
+    }
+}
+
+fn print_curves_csv(reports: &[RunReport]) {
+    println!("seed,time,gold,defense");
+    for report in reports {
+        for sample in &report.curve {
+            println!(
+                "{},{:.1},{},{}",
+                report.seed, sample.time, sample.gold, sample.defense
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn negative_durations_are_refused() {
+        for flag in ["--duration", "--sample"] {
+            let args = [flag.to_string(), "-1".to_string()];
+            assert!(SimulationOptions::parse(args).is_err());
+        }
+    }
+
+    #[test]
+    fn the_defensive_strategy_buys_wall_capacity() {
+        let walls = "defense = 5\nmax_defense = 20";
+        assert!(fixture::CONFIG.contains(walls));
+        let config = Config::parse(&fixture::CONFIG.replace(walls, "max_defense = 20")).unwrap();
+        let mut world = crate::setup_world(config, GameClock::manual(), GameRng::new(0));
+
+        let upgrade = choose_upgrade(&mut world, &Strategy::Defensive, 0);
+
+        assert_eq!(upgrade.as_deref(), Some("walls"));
+    }
+}
@@ -0,0 +1,106 @@
+use bevy_ecs::prelude::*;
+use serde::Deserialize;
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clock::GameClock,
+    components::{
+        ActiveModifiers, BoughtUpgrades, Defense, DefensePerSecond, EventMessage, FatalBlow,
+        GameRunning, GameState, Gold, GoldEarned, GoldPerSecond, LastClick, LastEventCheck,
+        LastSave, LastTick, MaxDefense, OfflineReport, RepairQueue, RunStarted, SelectedUpgrade,
+        Statistics, Totals, Wave,
+    },
+    game::Config,
+    rng::GameRng,
+};
+
+/// The `[start]` section: what every castle starts with.
+#[derive(Debug, Deserialize)]
+pub struct StartConfig {
+    pub gold: u64,
+    pub defense: u64,
+}
+
+/// Everything that belongs to a single castle and starts over with the next one.
+#[derive(Bundle)]
+pub struct RunBundle {
+    gold: Gold,
+    gold_ps: GoldPerSecond,
+    gold_earned: GoldEarned,
+    defense: Defense,
+    max_defense: MaxDefense,
+    defense_ps: DefensePerSecond,
+    repairs: RepairQueue,
+    bought_upgrades: BoughtUpgrades,
+    active_modifiers: ActiveModifiers,
+    last_tick: LastTick,
+    last_event_check: LastEventCheck,
+    last_click: LastClick,
+    last_save: LastSave,
+    event_message: EventMessage,
+    selected_upgrade: SelectedUpgrade,
+    game_running: GameRunning,
+    game_state: GameState,
+    offline_report: OfflineReport,
+    run_started: RunStarted,
+    wave: Wave,
+    fatal_blow: FatalBlow,
+}
+
+impl RunBundle {
+    /// A castle starting at `now`; both a new game and a restart begin here.
+    pub fn new(config: &Config, now: Instant) -> Self {
+        Self {
+            gold: Gold(config.start.gold),
+            gold_ps: GoldPerSecond(0),
+            gold_earned: GoldEarned::default(),
+            defense: Defense(config.start.defense),
+            max_defense: MaxDefense(config.walls.capacity),
+            defense_ps: DefensePerSecond(0),
+            repairs: RepairQueue::default(),
+            bought_upgrades: BoughtUpgrades::default(),
+            active_modifiers: ActiveModifiers::default(),
+            last_tick: LastTick(now),
+            last_event_check: LastEventCheck(now),
+            last_click: LastClick(now),
+            last_save: LastSave(now),
+            event_message: EventMessage {
+                message: String::new(),
+                timestamp: now,
+            },
+            selected_upgrade: SelectedUpgrade(config.upgrades[0].id.clone()),
+            game_running: GameRunning(true),
+            game_state: GameState::Playing,
+            offline_report: OfflineReport::default(),
+            run_started: RunStarted(now),
+            wave: Wave {
+                number: 1,
+                next_at: now + Duration::from_secs_f32(config.waves.first_wave),
+            },
+            fatal_blow: FatalBlow::default(),
+        }
+    }
+}
+
+/// Replaces the castle with a new one, keeping what carries over between
+/// runs: renown, achievements, lifetime statistics and the history.
+pub fn start_run(world: &mut World) {
+    let now = world.resource::<GameClock>().now();
+    let mut query = world.query::<(Entity, &mut Statistics, &Config)>();
+    let (entity, mut statistics, config) = query.single_mut(world);
+    statistics.run = Totals::default();
+    let run = RunBundle::new(config, now);
+    let rng = GameRng::for_new_run(config.rng.seed);
+    world.entity_mut(entity).insert(run);
+    world.insert_resource(rng);
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn Error>> {
+    if config.start.defense == 0 || config.start.defense > config.walls.capacity {
+        return Err("start.defense must be between 1 and walls.capacity".into());
+    }
+    Ok(())
+}
@@ -1,118 +1,179 @@
 use bevy_ecs::prelude::*;
 use rust_i18n::t;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::{
+    achievements::{self, RunProgress},
+    clock::GameClock,
     components::*,
-    game::{self, Config},
+    difficulty::Difficulty,
+    events::{self, EventContext, EventTarget},
+    game::{self, Config, UnlockProgress},
+    prestige, repair,
+    rng::GameRng,
+    save::WallTime,
+    waves::{self, WaveComposition},
 };
 
-pub fn update_per_second_system(
-    mut query: Query<(
-        &mut Gold,
-        &mut GoldPerSecond,
-        &mut Defense,
-        &mut DefensePerSecond,
-        &mut LastTick,
-        &BoughtUpgrades,
-        &Config,
-    )>,
-) {
+/// How long a message stays in the event bar, however often events come.
+pub const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// Most time played a single tick counts. Longer gaps were spent away, past
+/// what `offline.max_duration` credited, and are not played time.
+pub const MAX_TICK_PLAYED: Duration = Duration::from_secs(2);
+
+type PerSecondQuery<'a> = (
+    &'a mut Gold,
+    &'a mut GoldEarned,
+    &'a mut GoldPerSecond,
+    &'a mut Defense,
+    &'a mut MaxDefense,
+    &'a mut DefensePerSecond,
+    &'a mut LastTick,
+    &'a mut ActiveModifiers,
+    &'a mut RepairQueue,
+    &'a mut Statistics,
+    &'a BoughtUpgrades,
+    &'a Prestige,
+    &'a Config,
+);
+
+type EventsQuery<'a> = (
+    &'a mut Defense,
+    &'a MaxDefense,
+    &'a mut Gold,
+    &'a mut GoldEarned,
+    &'a mut BoughtUpgrades,
+    &'a mut ActiveModifiers,
+    &'a mut LastEventCheck,
+    &'a mut EventMessage,
+    &'a mut GameRunning,
+    &'a mut GameState,
+    &'a mut Statistics,
+    &'a mut FatalBlow,
+    &'a RunStarted,
+    &'a Prestige,
+    &'a Config,
+);
+
+pub fn update_per_second_system(mut query: Query<PerSecondQuery>, clock: Res<GameClock>) {
     for (
         mut gold,
+        mut gold_earned,
         mut gold_ps,
         mut defense,
+        mut max_defense,
         mut defense_ps,
         mut last_tick,
+        mut modifiers,
+        mut repairs,
+        mut statistics,
         bought_upgrades,
+        prestige,
         config,
     ) in query.iter_mut()
     {
-        let now = Instant::now();
+        let now = clock.now();
         let elapsed = now.duration_since(last_tick.0);
 
         if elapsed >= Duration::from_secs(1) {
-            defense.0 += game::calculate_defense_per_second(bought_upgrades, config);
-            defense_ps.0 = game::calculate_defense_per_second(bought_upgrades, config);
-            gold_ps.0 = game::calculate_gold_per_second(bought_upgrades, config);
+            modifiers.prune(now);
+            defense_ps.0 = (game::calculate_defense_per_second(bought_upgrades, config) as f32
+                * modifiers.factor(ModifierTarget::DefensePerSecond, now))
+                as u64;
+            gold_ps.0 = (game::calculate_gold_per_second(bought_upgrades, config) as f32
+                * modifiers.factor(ModifierTarget::GoldPerSecond, now)
+                * prestige::gold_factor(prestige, config)) as u64;
+            // Walls regenerate up to their capacity, and crumble to it if it shrank
+            max_defense.0 = game::calculate_max_defense(bought_upgrades, config);
+            defense.0 = (defense.0 + defense_ps.0).min(max_defense.0);
+            repair::work(
+                &mut repairs,
+                &mut defense.0,
+                max_defense.0,
+                repair::repair_speed(bought_upgrades, config),
+            );
             gold.0 += gold_ps.0;
+            gold_earned.0 += gold_ps.0;
             last_tick.0 = now;
+            statistics.record(|totals| {
+                totals.gold_from_income += gold_ps.0;
+                totals.seconds_played += elapsed.min(MAX_TICK_PLAYED).as_secs_f64();
+                totals.peak_defense = totals.peak_defense.max(defense.0);
+            });
         }
     }
 }
 
 pub fn handle_events_system(
-    mut query: Query<(
-        &mut Defense,
-        &mut Gold,
-        &mut LastEventCheck,
-        &mut EventMessage,
-        &mut GameRunning,
-        &mut GameState,
-        &Config,
-    )>,
+    mut query: Query<EventsQuery>,
+    clock: Res<GameClock>,
+    mut rng: ResMut<GameRng>,
 ) {
     for (
         mut defense,
+        max_defense,
         mut gold,
+        mut gold_earned,
+        mut bought_upgrades,
+        mut modifiers,
         mut last_event_check,
         mut event_message,
         mut game_running,
         mut game_state,
+        mut statistics,
+        mut fatal_blow,
+        run_started,
+        prestige,
         config,
     ) in query.iter_mut()
     {
-        let now = Instant::now();
-        let next_event_at = last_event_check.0
-            + Duration::from_millis((config.events.cooldown * 1000.0).round() as u64);
-        let hide_message_at = next_event_at - Duration::from_secs(1);
+        let now = clock.now();
+        let context = EventContext {
+            run_time: now.duration_since(run_started.0),
+            gold: gold.0,
+            bought_upgrades: &bought_upgrades,
+        };
+        let cooldown = Difficulty::at(config, &context).event_cooldown(config);
+        let next_event_at = last_event_check.0 + cooldown;
 
         // Clear old messages
-        if now >= hide_message_at {
+        if now >= event_message.timestamp + MESSAGE_DURATION {
             event_message.message.clear();
         }
 
         if now >= next_event_at {
-            let event = game::generate_random_event(config);
-            match event {
-                game::GameEvent::BanditRaid {
-                    defense_loss,
-                    message,
-                }
-                | game::GameEvent::SiegeCatapults {
-                    defense_loss,
-                    message,
-                }
-                | game::GameEvent::Sabotage {
-                    defense_loss,
-                    message,
-                } => {
-                    if defense.0 >= defense_loss {
-                        defense.0 -= defense_loss;
-                        event_message.message = t!(
-                            "game.events.defense_loss",
-                            message = message,
-                            amount = defense_loss
-                        )
-                        .to_string();
-                    } else {
-                        defense.0 = 0;
-                        event_message.message =
-                            t!("game.events.castle_fall", message = message).to_string();
+            match events::generate_random_event(config, &context, rng.as_mut()) {
+                Some(event) => {
+                    let target = EventTarget {
+                        gold: &mut gold.0,
+                        defense: &mut defense.0,
+                        max_defense: max_defense.0,
+                        damage_factor: prestige::damage_factor(prestige, config),
+                        bought_upgrades: &mut bought_upgrades,
+                        modifiers: &mut modifiers,
+                    };
+                    let outcome = events::apply_event(&event, target, now, config);
+                    gold_earned.0 += outcome.gold_gained;
+                    event_message.message = events::describe_event(&event, &outcome);
+                    statistics.record(|totals| {
+                        totals.gold_from_events += outcome.gold_gained;
+                        *totals.defense_lost.entry(event.id.clone()).or_default() +=
+                            outcome.defense_lost;
+                        if outcome.castle_fell {
+                            totals.castles_fallen += 1;
+                        } else {
+                            totals.events_survived += 1;
+                        }
+                    });
+
+                    if outcome.castle_fell {
                         game_running.0 = false;
                         *game_state = GameState::GameOver;
+                        fatal_blow.0 = Some(event.message.clone());
                     }
                 }
-                game::GameEvent::TradeOffer { gold_gain, message } => {
-                    gold.0 += gold_gain;
-                    event_message.message = t!(
-                        "game.events.trade_gain",
-                        message = message,
-                        amount = gold_gain
-                    )
-                    .to_string();
-                }
-                game::GameEvent::Nothing => {
+                None => {
                     event_message.message = t!("game.events.nothing").to_string();
                 }
             }
@@ -122,26 +183,1280 @@ pub fn handle_events_system(
     }
 }
 
-pub fn handle_upgrade_system(
-    mut query: Query<(
-        &mut Gold,
-        &mut Defense,
-        &mut BoughtUpgrades,
-        &Upgrades,
-        &SelectedUpgrade,
-        &Config,
-    )>,
-) {
-    for (mut gold, mut defense, mut bought_upgrades, upgrades, selected, config) in query.iter_mut()
+type WaveQuery<'a> = (
+    &'a mut Wave,
+    &'a mut Gold,
+    &'a mut GoldEarned,
+    &'a mut Defense,
+    &'a mut EventMessage,
+    &'a mut GameRunning,
+    &'a mut GameState,
+    &'a mut Statistics,
+    &'a mut FatalBlow,
+    &'a BoughtUpgrades,
+    &'a Config,
+);
+
+pub fn wave_system(mut query: Query<WaveQuery>, clock: Res<GameClock>) {
+    for (
+        mut wave,
+        mut gold,
+        mut gold_earned,
+        mut defense,
+        mut event_message,
+        mut game_running,
+        mut game_state,
+        mut statistics,
+        mut fatal_blow,
+        bought_upgrades,
+        config,
+    ) in query.iter_mut()
+    {
+        let now = clock.now();
+        if !game_state.in_run() || now < wave.next_at {
+            continue;
+        }
+
+        let composition = WaveComposition::new(wave.number, config);
+        let outcome = waves::resolve_wave(
+            &composition,
+            &mut gold.0,
+            &mut defense.0,
+            bought_upgrades,
+            config,
+        );
+        gold_earned.0 += outcome.reward;
+        event_message.message = waves::describe_wave(wave.number, &outcome);
+        event_message.timestamp = now;
+        statistics.record(|totals| {
+            totals.gold_from_waves += outcome.reward;
+            totals.defense_lost_to_waves += outcome.defense_lost;
+            if outcome.castle_fell {
+                totals.castles_fallen += 1;
+            } else {
+                totals.waves_repelled += 1;
+            }
+        });
+
+        if outcome.castle_fell {
+            game_running.0 = false;
+            *game_state = GameState::GameOver;
+            fatal_blow.0 = Some(t!("game.game_over.fatal_wave", number = wave.number).to_string());
+        }
+
+        wave.number += 1;
+        wave.next_at += waves::wave_interval(config);
+    }
+}
+
+type AchievementsQuery<'a> = (
+    &'a mut Achievements,
+    &'a mut AchievementToast,
+    &'a GoldEarned,
+    &'a BoughtUpgrades,
+    &'a Statistics,
+    &'a RunStarted,
+    &'a GameState,
+    &'a Config,
+);
+
+/// Unlocks the achievements the run has just reached.
+pub fn achievements_system(mut query: Query<AchievementsQuery>, clock: Res<GameClock>) {
+    for (
+        mut achievements,
+        mut toast,
+        gold_earned,
+        bought_upgrades,
+        statistics,
+        run_started,
+        game_state,
+        config,
+    ) in query.iter_mut()
+    {
+        if !game_state.in_run() {
+            continue;
+        }
+
+        let now = clock.now();
+        let progress = RunProgress {
+            gold_earned: gold_earned.0,
+            bought_upgrades,
+            events_survived: statistics.run.events_survived,
+            run_time: now.duration_since(run_started.0),
+        };
+        for achievement in achievements::newly_unlocked(&achievements, &progress, config) {
+            achievements
+                .unlocked
+                .insert(achievement.id.clone(), WallTime::now());
+            achievements.unsaved = true;
+            toast.0 = Some((achievement.id.clone(), now));
+        }
+    }
+}
+
+type UpgradeQuery<'a> = (
+    &'a mut Gold,
+    &'a mut Defense,
+    &'a mut MaxDefense,
+    &'a mut GoldPerSecond,
+    &'a mut DefensePerSecond,
+    &'a mut BoughtUpgrades,
+    &'a mut EventMessage,
+    &'a ActiveModifiers,
+    &'a GoldEarned,
+    &'a RunStarted,
+    &'a mut Statistics,
+    &'a SelectedUpgrade,
+    &'a BuyQuantity,
+    &'a Prestige,
+    &'a Config,
+);
+
+pub fn handle_upgrade_system(mut query: Query<UpgradeQuery>, clock: Res<GameClock>) {
+    for (
+        mut gold,
+        mut defense,
+        mut max_defense,
+        mut gold_ps,
+        mut defense_ps,
+        mut bought_upgrades,
+        mut event_message,
+        modifiers,
+        gold_earned,
+        run_started,
+        mut statistics,
+        selected,
+        quantity,
+        prestige,
+        config,
+    ) in query.iter_mut()
+    {
+        let now = clock.now();
+        let Some(upgrade) = config.upgrade(&selected.0) else {
+            continue;
+        };
+
+        let progress = UnlockProgress {
+            bought_upgrades: &bought_upgrades,
+            gold_earned: gold_earned.0,
+            run_time: now.duration_since(run_started.0),
+        };
+        if let Some(requirement) = game::missing_requirement(upgrade, &progress) {
+            event_message.message = t!(
+                "game.upgrades.refused_locked",
+                name = upgrade.name,
+                requirement = requirement.describe(&progress, config)
+            )
+            .to_string();
+            event_message.timestamp = now;
+            continue;
+        }
+
+        let level = bought_upgrades.get_count(&upgrade.id);
+        if upgrade.is_maxed(level) {
+            event_message.message = t!(
+                "game.upgrades.refused_maxed",
+                name = upgrade.name,
+                level = level
+            )
+            .to_string();
+            event_message.timestamp = now;
+            continue;
+        }
+
+        let batch = game::plan_batch(upgrade, level, gold.0, *quantity);
+        if !game::can_afford_upgrade(gold.0, batch.cost) {
+            continue;
+        }
+
+        gold.0 -= batch.cost;
+        for _ in 0..batch.count {
+            bought_upgrades.increment(&upgrade.id);
+            config.effects.apply_purchase(&upgrade.id, &mut defense.0);
+        }
+        statistics.record(|totals| totals.upgrades_bought += batch.count as u64);
+        max_defense.0 = game::calculate_max_defense(&bought_upgrades, config);
+        defense.0 = defense.0.min(max_defense.0);
+
+        // Show the new rates right away instead of on the next tick
+        gold_ps.0 = (game::calculate_gold_per_second(&bought_upgrades, config) as f32
+            * modifiers.factor(ModifierTarget::GoldPerSecond, now)
+            * prestige::gold_factor(prestige, config)) as u64;
+        defense_ps.0 = (game::calculate_defense_per_second(&bought_upgrades, config) as f32
+            * modifiers.factor(ModifierTarget::DefensePerSecond, now))
+            as u64;
+    }
+}
+
+type SellQuery<'a> = (
+    &'a mut Gold,
+    &'a mut Defense,
+    &'a mut MaxDefense,
+    &'a mut GoldPerSecond,
+    &'a mut DefensePerSecond,
+    &'a mut BoughtUpgrades,
+    &'a mut EventMessage,
+    &'a ActiveModifiers,
+    &'a SelectedUpgrade,
+    &'a Prestige,
+    &'a Config,
+);
+
+/// Sells one unit of the selected upgrade for part of what the last one cost.
+pub fn handle_sell_system(mut query: Query<SellQuery>, clock: Res<GameClock>) {
+    for (
+        mut gold,
+        mut defense,
+        mut max_defense,
+        mut gold_ps,
+        mut defense_ps,
+        mut bought_upgrades,
+        mut event_message,
+        modifiers,
+        selected,
+        prestige,
+        config,
+    ) in query.iter_mut()
+    {
+        let now = clock.now();
+        let Some(upgrade) = config.upgrade(&selected.0) else {
+            continue;
+        };
+
+        event_message.timestamp = now;
+        if bought_upgrades.remove(&upgrade.id, 1) == 0 {
+            event_message.message =
+                t!("game.upgrades.nothing_to_sell", name = upgrade.name).to_string();
+            continue;
+        }
+
+        let level = bought_upgrades.get_count(&upgrade.id);
+        let refund = (upgrade.cost(level) as f32 * config.sell.refund) as u64;
+        gold.0 += refund;
+        config.effects.apply_sale(&upgrade.id, &mut defense.0);
+        max_defense.0 = game::calculate_max_defense(&bought_upgrades, config);
+        defense.0 = defense.0.min(max_defense.0);
+        event_message.message =
+            t!("game.upgrades.sold", name = upgrade.name, amount = refund).to_string();
+
+        gold_ps.0 = (game::calculate_gold_per_second(&bought_upgrades, config) as f32
+            * modifiers.factor(ModifierTarget::GoldPerSecond, now)
+            * prestige::gold_factor(prestige, config)) as u64;
+        defense_ps.0 = (game::calculate_defense_per_second(&bought_upgrades, config) as f32
+            * modifiers.factor(ModifierTarget::DefensePerSecond, now))
+            as u64;
+    }
+}
+
+type RepairQuery<'a> = (
+    &'a mut Gold,
+    &'a mut RepairQueue,
+    &'a mut EventMessage,
+    &'a Defense,
+    &'a MaxDefense,
+    &'a Config,
+);
+
+/// Pays for a batch of wall repairs and queues it.
+pub fn handle_repair_system(mut query: Query<RepairQuery>, clock: Res<GameClock>) {
+    for (mut gold, mut repairs, mut event_message, defense, max_defense, config) in query.iter_mut()
     {
-        let cost = selected.0.cost(config, &bought_upgrades);
+        event_message.timestamp = clock.now();
+        let points = repair::order_size(defense.0, max_defense.0, &repairs, config);
+        if points == 0 {
+            event_message.message = t!("game.repair.nothing").to_string();
+            continue;
+        }
 
+        let cost = repair::order_cost(points, config);
         if !game::can_afford_upgrade(gold.0, cost) {
+            event_message.message = t!("game.repair.refused_gold", cost = cost).to_string();
             continue;
         }
 
         gold.0 -= cost;
-        bought_upgrades.increment(&selected.0);
-        upgrades.apply_defense(&mut defense, config);
+        repair::order(&mut repairs, points);
+        event_message.message = t!("game.repair.ordered", amount = points, cost = cost).to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::RunSystemOnce;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{fixture, game::Requirement};
+
+    fn setup() -> World {
+        rust_i18n::set_locale("ru");
+        crate::setup_world(fixture::config(), GameClock::manual(), GameRng::new(0))
+    }
+
+    /// Gold and defense every castle starts with.
+    fn starting(world: &mut World) -> (u64, u64) {
+        let config = world.query::<&Config>().single(world);
+        (config.start.gold, config.start.defense)
+    }
+
+    fn advance(world: &mut World, by: Duration) {
+        world.resource_mut::<GameClock>().advance(by);
+    }
+
+    fn buy(world: &mut World, upgrade: &str, count: u32) {
+        let mut bought_upgrades = world.query::<&mut BoughtUpgrades>().single_mut(world);
+        for _ in 0..count {
+            bought_upgrades.increment(upgrade);
+        }
+    }
+
+    /// Selects `upgrade` and presses buy.
+    fn try_purchase(world: &mut World, upgrade: &str) {
+        world.query::<&mut SelectedUpgrade>().single_mut(world).0 = upgrade.to_string();
+        world.run_system_once(handle_upgrade_system).unwrap();
+    }
+
+    /// Buys one `upgrade` late into a rich run, where only upgrade
+    /// requirements can still be missing.
+    fn purchase(world: &mut World, upgrade: &str) {
+        advance(world, Duration::from_secs(3600));
+        let (mut gold, mut gold_earned) = world
+            .query::<(&mut Gold, &mut GoldEarned)>()
+            .single_mut(world);
+        gold.0 = 1_000_000;
+        gold_earned.0 = 1_000_000;
+        try_purchase(world, upgrade);
+    }
+
+    fn upgrade_defense(world: &mut World, upgrade: &str) -> u64 {
+        let config = world.query::<&Config>().single(world);
+        config.upgrade(upgrade).unwrap().defense
+    }
+
+    fn assert_purchase_adds_defense(upgrade: &str) {
+        let mut world = setup();
+        let (_, start) = starting(&mut world);
+        let expected = upgrade_defense(&mut world, upgrade);
+        assert!(expected > 0, "{upgrade} should be configured with defense");
+
+        purchase(&mut world, upgrade);
+        assert_eq!(world.query::<&Defense>().single(&world).0, start + expected);
+        purchase(&mut world, upgrade);
+        assert_eq!(
+            world.query::<&Defense>().single(&world).0,
+            start + 2 * expected
+        );
+    }
+
+    #[test]
+    fn catapult_adds_its_defense() {
+        assert_purchase_adds_defense("catapult");
+    }
+
+    #[test]
+    fn archer_adds_its_defense() {
+        assert_purchase_adds_defense("archer");
+    }
+
+    #[test]
+    fn warrior_adds_its_defense() {
+        assert_purchase_adds_defense("warrior");
+    }
+
+    #[test]
+    fn oil_adds_its_defense() {
+        assert_purchase_adds_defense("oil");
+    }
+
+    #[test]
+    fn officer_adds_defense_and_defense_per_second() {
+        let mut world = setup();
+        let (_, start) = starting(&mut world);
+        let expected = upgrade_defense(&mut world, "officer");
+
+        purchase(&mut world, "officer");
+        purchase(&mut world, "officer");
+
+        let (defense, defense_ps, config) = world
+            .query::<(&Defense, &DefensePerSecond, &Config)>()
+            .single(&world);
+        let per_second = config
+            .upgrade("officer")
+            .unwrap()
+            .defense_per_second
+            .value(2);
+        assert!(per_second > 0);
+        assert_eq!(defense_ps.0, per_second);
+        assert_eq!(defense.0, start + 2 * expected);
+    }
+
+    #[test]
+    fn trade_hall_levels_replace_gold_per_second() {
+        let mut world = setup();
+        let (_, start) = starting(&mut world);
+
+        for level in 1..=3 {
+            purchase(&mut world, "trade_hall");
+            let (gold_ps, defense, config) = world
+                .query::<(&GoldPerSecond, &Defense, &Config)>()
+                .single(&world);
+            let expected = config
+                .upgrade("trade_hall")
+                .unwrap()
+                .gold_per_second
+                .value(level);
+            assert_eq!(gold_ps.0, expected);
+            assert_eq!(defense.0, start);
+        }
+    }
+
+    #[test]
+    fn midas_hand_shortens_the_click_cooldown() {
+        let mut world = setup();
+        let (trade_hall, level) = midas_hand_requirement(&mut world);
+        buy(&mut world, &trade_hall, level);
+        let (bought_upgrades, config) = world.query::<(&BoughtUpgrades, &Config)>().single(&world);
+        let before = game::calculate_click_cooldown(bought_upgrades, config);
+        let reduction = config
+            .upgrade("midas_hand")
+            .unwrap()
+            .click_cooldown_reduction;
+
+        purchase(&mut world, "midas_hand");
+
+        let (bought_upgrades, config) = world.query::<(&BoughtUpgrades, &Config)>().single(&world);
+        let after = game::calculate_click_cooldown(bought_upgrades, config);
+        assert!((before - after - reduction).abs() < 1e-6);
+    }
+
+    #[test]
+    fn purchase_applies_the_selected_upgrade_not_the_first() {
+        let mut world = setup();
+        let (upgrades, config) = world.query::<(&Upgrades, &Config)>().single(&world);
+        let first = config.effects.get(&upgrades.0[0]).len();
+        assert!(first > 0);
+        let (_, start) = starting(&mut world);
+
+        purchase(&mut world, "trade_hall");
+        assert_eq!(world.query::<&Defense>().single(&world).0, start);
+    }
+
+    /// The upgrade and level the Midas hand waits for.
+    fn midas_hand_requirement(world: &mut World) -> (String, u32) {
+        let config = world.query::<&Config>().single(world);
+        match &config.upgrade("midas_hand").unwrap().requires[..] {
+            [Requirement::Upgrade { upgrade, level }] => (upgrade.clone(), *level),
+            requires => panic!("unexpected midas_hand requirements {requires:?}"),
+        }
+    }
+
+    #[test]
+    fn locked_upgrades_are_refused_with_the_missing_requirement() {
+        let mut world = setup();
+        let (trade_hall, level) = midas_hand_requirement(&mut world);
+        buy(&mut world, &trade_hall, level - 1);
+        world.query::<&mut Gold>().single_mut(&mut world).0 = 1_000_000;
+
+        try_purchase(&mut world, "midas_hand");
+
+        let (gold, bought_upgrades, event_message, config) = world
+            .query::<(&Gold, &BoughtUpgrades, &EventMessage, &Config)>()
+            .single(&world);
+        let missing = t!(
+            "game.upgrades.requirements.upgrade",
+            name = config.upgrade(&trade_hall).unwrap().name,
+            level = level,
+            current = level - 1
+        );
+        assert_eq!(gold.0, 1_000_000);
+        assert_eq!(bought_upgrades.get_count("midas_hand"), 0);
+        assert!(event_message.message.contains(&*missing));
+    }
+
+    #[test]
+    fn time_and_earnings_requirements_unlock_upgrades() {
+        let mut world = setup();
+        let (survived, gold_earned) = {
+            let config = world.query::<&Config>().single(&world);
+            let requires = |id: &str| &config.upgrade(id).unwrap().requires[..];
+            match (requires("oil"), requires("officer")) {
+                (
+                    [Requirement::Survived { survived }],
+                    [Requirement::GoldEarned { gold_earned }],
+                ) => (*survived, *gold_earned),
+                requires => panic!("unexpected oil and officer requirements {requires:?}"),
+            }
+        };
+        world.query::<&mut Gold>().single_mut(&mut world).0 = 1_000_000;
+
+        try_purchase(&mut world, "oil");
+        try_purchase(&mut world, "officer");
+        let bought_upgrades = world.query::<&BoughtUpgrades>().single(&world);
+        assert_eq!(bought_upgrades.get_count("oil"), 0);
+        assert_eq!(bought_upgrades.get_count("officer"), 0);
+
+        advance(&mut world, Duration::from_secs_f32(survived));
+        world.query::<&mut GoldEarned>().single_mut(&mut world).0 = gold_earned;
+        try_purchase(&mut world, "oil");
+        try_purchase(&mut world, "officer");
+        let bought_upgrades = world.query::<&BoughtUpgrades>().single(&world);
+        assert_eq!(bought_upgrades.get_count("oil"), 1);
+        assert_eq!(bought_upgrades.get_count("officer"), 1);
+    }
+
+    #[test]
+    fn maxed_upgrades_are_refused() {
+        let mut world = setup();
+        let max_level = {
+            let config = world.query::<&Config>().single(&world);
+            config.upgrade("trade_hall").unwrap().max_level().unwrap()
+        };
+
+        for _ in 0..max_level {
+            purchase(&mut world, "trade_hall");
+        }
+        let gold_ps = world.query::<&GoldPerSecond>().single(&world).0;
+        purchase(&mut world, "trade_hall");
+
+        let (gold, gold_ps_after, bought_upgrades, event_message, config) = world
+            .query::<(
+                &Gold,
+                &GoldPerSecond,
+                &BoughtUpgrades,
+                &EventMessage,
+                &Config,
+            )>()
+            .single(&world);
+        assert_eq!(bought_upgrades.get_count("trade_hall"), max_level);
+        assert_eq!(gold.0, 1_000_000);
+        assert_eq!(gold_ps_after.0, gold_ps);
+        assert_eq!(
+            event_message.message,
+            t!(
+                "game.upgrades.refused_maxed",
+                name = config.upgrade("trade_hall").unwrap().name,
+                level = max_level
+            )
+        );
+    }
+
+    #[test]
+    fn unit_prices_follow_their_cost_curve() {
+        let mut world = setup();
+        let mut spent = Vec::new();
+
+        for _ in 0..3 {
+            purchase(&mut world, "warrior");
+            spent.push(1_000_000 - world.query::<&Gold>().single(&world).0);
+        }
+
+        let config = world.query::<&Config>().single(&world);
+        let warrior = config.upgrade("warrior").unwrap();
+        assert_eq!(spent, [warrior.cost(0), warrior.cost(1), warrior.cost(2)]);
+        assert!(spent[0] < spent[1] && spent[1] < spent[2]);
+    }
+
+    fn set_quantity(world: &mut World, quantity: BuyQuantity) {
+        *world.query::<&mut BuyQuantity>().single_mut(world) = quantity;
+    }
+
+    #[test]
+    fn bulk_purchase_pays_the_summed_curve() {
+        let mut world = setup();
+        set_quantity(&mut world, BuyQuantity::Ten);
+
+        purchase(&mut world, "warrior");
+
+        let (gold, defense, bought_upgrades, config) = world
+            .query::<(&Gold, &Defense, &BoughtUpgrades, &Config)>()
+            .single(&world);
+        let warrior = config.upgrade("warrior").unwrap();
+        let expected: u64 = (0..10).map(|level| warrior.cost(level)).sum();
+        assert_eq!(bought_upgrades.get_count("warrior"), 10);
+        assert_eq!(gold.0, 1_000_000 - expected);
+        assert_eq!(defense.0, config.start.defense + 10 * warrior.defense);
+    }
+
+    #[test]
+    fn bulk_purchase_is_refused_when_the_batch_is_unaffordable() {
+        let mut world = setup();
+        let (start, _) = starting(&mut world);
+        set_quantity(&mut world, BuyQuantity::Hundred);
+
+        try_purchase(&mut world, "warrior");
+
+        let (gold, bought_upgrades) = world.query::<(&Gold, &BoughtUpgrades)>().single(&world);
+        assert_eq!(gold.0, start);
+        assert_eq!(bought_upgrades.get_count("warrior"), 0);
+    }
+
+    #[test]
+    fn max_mode_buys_as_many_as_affordable() {
+        let mut world = setup();
+        set_quantity(&mut world, BuyQuantity::Max);
+
+        try_purchase(&mut world, "warrior");
+
+        let (gold, bought_upgrades, config) = world
+            .query::<(&Gold, &BoughtUpgrades, &Config)>()
+            .single(&world);
+        let warrior = config.upgrade("warrior").unwrap();
+        let count = bought_upgrades.get_count("warrior");
+        let spent: u64 = (0..count).map(|level| warrior.cost(level)).sum();
+        assert!(count > 1);
+        assert_eq!(gold.0, config.start.gold - spent);
+        assert!(gold.0 < warrior.cost(count));
+    }
+
+    #[test]
+    fn bulk_purchase_stops_at_the_level_cap() {
+        let mut world = setup();
+        set_quantity(&mut world, BuyQuantity::Ten);
+
+        purchase(&mut world, "trade_hall");
+
+        let (bought_upgrades, config) = world.query::<(&BoughtUpgrades, &Config)>().single(&world);
+        let max_level = config.upgrade("trade_hall").unwrap().max_level().unwrap();
+        assert_eq!(bought_upgrades.get_count("trade_hall"), max_level);
+    }
+
+    fn sell(world: &mut World, upgrade: &str) {
+        world.query::<&mut SelectedUpgrade>().single_mut(world).0 = upgrade.to_string();
+        world.run_system_once(handle_sell_system).unwrap();
+    }
+
+    #[test]
+    fn selling_refunds_part_of_the_price_and_removes_defense() {
+        let mut world = setup();
+        purchase(&mut world, "catapult");
+        purchase(&mut world, "catapult");
+        world.query::<&mut Gold>().single_mut(&mut world).0 = 0;
+
+        sell(&mut world, "catapult");
+
+        let (gold, defense, bought_upgrades, config) = world
+            .query::<(&Gold, &Defense, &BoughtUpgrades, &Config)>()
+            .single(&world);
+        let catapult = config.upgrade("catapult").unwrap();
+        let refund = (catapult.cost(1) as f32 * config.sell.refund) as u64;
+        assert_eq!(bought_upgrades.get_count("catapult"), 1);
+        assert_eq!(gold.0, refund);
+        assert_eq!(defense.0, config.start.defense + catapult.defense);
+    }
+
+    #[test]
+    fn selling_a_level_lowers_the_income() {
+        let mut world = setup();
+        purchase(&mut world, "trade_hall");
+        purchase(&mut world, "trade_hall");
+
+        sell(&mut world, "trade_hall");
+
+        let (gold_ps, config) = world.query::<(&GoldPerSecond, &Config)>().single(&world);
+        let expected = config
+            .upgrade("trade_hall")
+            .unwrap()
+            .gold_per_second
+            .value(1);
+        assert_eq!(gold_ps.0, expected);
+    }
+
+    #[test]
+    fn selling_nothing_changes_nothing() {
+        let mut world = setup();
+        let (start, _) = starting(&mut world);
+
+        sell(&mut world, "warrior");
+
+        let (gold, bought_upgrades) = world.query::<(&Gold, &BoughtUpgrades)>().single(&world);
+        assert_eq!(gold.0, start);
+        assert_eq!(bought_upgrades.get_count("warrior"), 0);
+    }
+
+    #[test]
+    fn per_second_income_waits_for_a_full_second() {
+        let mut world = setup();
+        let (start, _) = starting(&mut world);
+        buy(&mut world, "trade_hall", 1);
+
+        advance(&mut world, Duration::from_millis(999));
+        world.run_system_once(update_per_second_system).unwrap();
+        assert_eq!(world.query::<&Gold>().single(&world).0, start);
+
+        advance(&mut world, Duration::from_millis(1));
+        world.run_system_once(update_per_second_system).unwrap();
+        let (gold, gold_ps, config) = world
+            .query::<(&Gold, &GoldPerSecond, &Config)>()
+            .single(&world);
+        let income = config
+            .upgrade("trade_hall")
+            .unwrap()
+            .gold_per_second
+            .value(1);
+        assert_eq!(gold_ps.0, income);
+        assert_eq!(gold.0, start + income);
+    }
+
+    #[test]
+    fn officers_add_defense_every_second() {
+        let mut world = setup();
+        buy(&mut world, "officer", 3);
+
+        for _ in 0..4 {
+            advance(&mut world, Duration::from_secs(1));
+            world.run_system_once(update_per_second_system).unwrap();
+        }
+
+        let (defense, defense_ps, config) = world
+            .query::<(&Defense, &DefensePerSecond, &Config)>()
+            .single(&world);
+        let per_second = config
+            .upgrade("officer")
+            .unwrap()
+            .defense_per_second
+            .value(3);
+        assert_eq!(defense_ps.0, per_second);
+        assert_eq!(defense.0, config.start.defense + 4 * per_second);
+    }
+
+    #[test]
+    fn defense_regenerates_up_to_the_wall_capacity() {
+        let mut world = setup();
+        buy(&mut world, "officer", 30);
+
+        for _ in 0..2 {
+            advance(&mut world, Duration::from_secs(1));
+            world.run_system_once(update_per_second_system).unwrap();
+        }
+
+        let (defense, max_defense, config) = world
+            .query::<(&Defense, &MaxDefense, &Config)>()
+            .single(&world);
+        assert_eq!(max_defense.0, config.walls.capacity);
+        assert_eq!(defense.0, max_defense.0);
+    }
+
+    #[test]
+    fn walls_raise_the_capacity_and_selling_them_lowers_it() {
+        let mut world = setup();
+        let (capacity, walls) = {
+            let config = world.query::<&Config>().single(&world);
+            let walls = config.upgrade("walls").unwrap();
+            (config.walls.capacity, walls.max_defense)
+        };
+
+        purchase(&mut world, "walls");
+        purchase(&mut world, "walls");
+        assert_eq!(
+            world.query::<&MaxDefense>().single(&world).0,
+            capacity + 2 * walls
+        );
+
+        world.query::<&mut Defense>().single_mut(&mut world).0 = capacity + 2 * walls;
+        sell(&mut world, "walls");
+        let (defense, max_defense) = world.query::<(&Defense, &MaxDefense)>().single(&world);
+        assert_eq!(max_defense.0, capacity + walls);
+        assert_eq!(defense.0, max_defense.0);
+    }
+
+    #[test]
+    fn purchases_do_not_fill_the_walls_past_capacity() {
+        let mut world = setup();
+        set_quantity(&mut world, BuyQuantity::Hundred);
+
+        purchase(&mut world, "warrior");
+
+        let (defense, max_defense) = world.query::<(&Defense, &MaxDefense)>().single(&world);
+        assert_eq!(defense.0, max_defense.0);
+    }
+
+    fn tick(world: &mut World, seconds: u32) {
+        for _ in 0..seconds {
+            advance(world, Duration::from_secs(1));
+            world.run_system_once(update_per_second_system).unwrap();
+        }
+    }
+
+    #[test]
+    fn repairs_are_paid_up_front_and_mended_over_time() {
+        let mut world = setup();
+        world.query::<&mut Defense>().single_mut(&mut world).0 = 50;
+
+        world.run_system_once(handle_repair_system).unwrap();
+
+        let (gold, repairs, config) = world
+            .query::<(&Gold, &RepairQueue, &Config)>()
+            .single(&world);
+        let (batch, speed) = (config.repair.batch, config.repair.speed);
+        assert_eq!(
+            gold.0,
+            config.start.gold - repair::order_cost(batch, config)
+        );
+        assert_eq!(repairs.pending(), batch);
+
+        tick(&mut world, 1);
+        assert_eq!(world.query::<&Defense>().single(&world).0, 50 + speed);
+
+        tick(&mut world, batch as u32);
+        let (defense, repairs) = world.query::<(&Defense, &RepairQueue)>().single(&world);
+        assert_eq!(defense.0, 50 + batch);
+        assert!(repairs.0.is_empty());
+    }
+
+    #[test]
+    fn whole_walls_need_no_repair() {
+        let mut world = setup();
+        let (start, _) = starting(&mut world);
+        let max_defense = world.query::<&MaxDefense>().single(&world).0;
+        world.query::<&mut Defense>().single_mut(&mut world).0 = max_defense;
+
+        world.run_system_once(handle_repair_system).unwrap();
+
+        let (gold, repairs) = world.query::<(&Gold, &RepairQueue)>().single(&world);
+        assert_eq!(gold.0, start);
+        assert!(repairs.0.is_empty());
+    }
+
+    #[test]
+    fn repairs_are_refused_without_the_gold() {
+        let mut world = setup();
+        world.query::<&mut Gold>().single_mut(&mut world).0 = 1;
+
+        world.run_system_once(handle_repair_system).unwrap();
+
+        let (gold, repairs) = world.query::<(&Gold, &RepairQueue)>().single(&world);
+        assert_eq!(gold.0, 1);
+        assert!(repairs.0.is_empty());
+    }
+
+    #[test]
+    fn masons_speed_up_repairs() {
+        let mut world = setup();
+        purchase(&mut world, "masons");
+        world.query::<&mut Defense>().single_mut(&mut world).0 = 50;
+        world.run_system_once(handle_repair_system).unwrap();
+
+        tick(&mut world, 1);
+
+        let (defense, config) = world.query::<(&Defense, &Config)>().single(&world);
+        let masons = config.upgrade("masons").unwrap().repair_speed;
+        assert_eq!(defense.0, 50 + config.repair.speed + masons);
+    }
+
+    #[test]
+    fn prestige_perks_raise_gold_per_second() {
+        let mut world = setup();
+        buy(&mut world, "trade_hall", 1);
+        world
+            .query::<&mut Prestige>()
+            .single_mut(&mut world)
+            .perks
+            .insert("royal_charter".to_string(), 5);
+
+        tick(&mut world, 1);
+
+        let (gold_ps, config) = world.query::<(&GoldPerSecond, &Config)>().single(&world);
+        let base = config
+            .upgrade("trade_hall")
+            .unwrap()
+            .gold_per_second
+            .value(1);
+        let perks = &config.prestige.perks;
+        let amount = perks
+            .iter()
+            .find(|perk| perk.id == "royal_charter")
+            .unwrap()
+            .amount;
+        assert_eq!(gold_ps.0, (base as f32 * (1.0 + 5.0 * amount)) as u64);
+    }
+
+    fn unlocked(world: &mut World) -> Vec<String> {
+        let achievements = world.query::<&Achievements>().single(world);
+        let mut unlocked: Vec<String> = achievements.unlocked.keys().cloned().collect();
+        unlocked.sort();
+        unlocked
+    }
+
+    #[test]
+    fn achievements_unlock_once_and_raise_a_toast() {
+        let mut world = setup();
+        world.run_system_once(achievements_system).unwrap();
+        assert!(unlocked(&mut world).is_empty());
+
+        world.query::<&mut GoldEarned>().single_mut(&mut world).0 = 1;
+        world.run_system_once(achievements_system).unwrap();
+        assert_eq!(unlocked(&mut world), ["first_coin"]);
+        let (achievements, toast) = world
+            .query::<(&Achievements, &AchievementToast)>()
+            .single(&world);
+        assert!(achievements.unsaved);
+        assert_eq!(toast.0.as_ref().unwrap().0, "first_coin");
+
+        world
+            .query::<&mut AchievementToast>()
+            .single_mut(&mut world)
+            .0 = None;
+        world.run_system_once(achievements_system).unwrap();
+        assert!(world
+            .query::<&AchievementToast>()
+            .single(&world)
+            .0
+            .is_none());
+    }
+
+    #[test]
+    fn maxing_midas_hand_unlocks_its_achievement() {
+        let mut world = setup();
+        let (trade_hall, level) = midas_hand_requirement(&mut world);
+        buy(&mut world, &trade_hall, level);
+        let max_level = {
+            let config = world.query::<&Config>().single(&world);
+            config.upgrade("midas_hand").unwrap().max_level().unwrap()
+        };
+        buy(&mut world, "midas_hand", max_level);
+
+        world.run_system_once(achievements_system).unwrap();
+
+        assert!(unlocked(&mut world).contains(&"golden_touch".to_string()));
+    }
+
+    fn statistics(world: &mut World) -> (Totals, Totals) {
+        let statistics = world.query::<&Statistics>().single(world);
+        (statistics.run.clone(), statistics.lifetime.clone())
+    }
+
+    #[test]
+    fn clicks_are_recorded_for_the_run_and_for_all_time() {
+        let mut world = setup();
+        advance(&mut world, Duration::from_secs(5));
+        crate::input::handle_click(&mut world);
+        // Still cooling down
+        crate::input::handle_click(&mut world);
+
+        let (run, lifetime) = statistics(&mut world);
+        assert_eq!((run.clicks, run.gold_from_clicks), (1, 1));
+        assert_eq!((lifetime.clicks, lifetime.gold_from_clicks), (1, 1));
+    }
+
+    #[test]
+    fn income_and_time_played_are_recorded_every_second() {
+        let mut world = setup();
+        buy(&mut world, "trade_hall", 1);
+        tick(&mut world, 3);
+
+        let gold_ps = world.query::<&GoldPerSecond>().single(&world).0;
+        assert!(gold_ps > 0);
+        let (run, lifetime) = statistics(&mut world);
+        assert_eq!(run.gold_from_income, 3 * gold_ps);
+        assert_eq!(run.seconds_played, 3.0);
+        assert_eq!(run.peak_defense, world.query::<&Defense>().single(&world).0);
+        assert_eq!(lifetime.gold_from_income, run.gold_from_income);
+    }
+
+    #[test]
+    fn uncredited_time_away_is_not_time_played() {
+        let mut world = setup();
+        advance(&mut world, Duration::from_secs(2 * 3600));
+
+        world.run_system_once(update_per_second_system).unwrap();
+
+        let (run, lifetime) = statistics(&mut world);
+        assert_eq!(run.seconds_played, MAX_TICK_PLAYED.as_secs_f64());
+        assert_eq!(lifetime.seconds_played, run.seconds_played);
+    }
+
+    #[test]
+    fn events_record_what_they_cost_the_castle() {
+        let mut world = setup();
+        let (mut defense, mut max_defense) = world
+            .query::<(&mut Defense, &mut MaxDefense)>()
+            .single_mut(&mut world);
+        (defense.0, max_defense.0) = (1_000_000, 1_000_000);
+
+        for _ in 0..50 {
+            advance(&mut world, Duration::from_secs(10));
+            world.run_system_once(handle_events_system).unwrap();
+        }
+
+        let lost = 1_000_000 - world.query::<&Defense>().single(&world).0;
+        let (run, lifetime) = statistics(&mut world);
+        assert!(run.events_survived > 0);
+        assert_eq!(run.castles_fallen, 0);
+        assert!(run.defense_lost.values().sum::<u64>() >= lost);
+        assert_eq!(lifetime.events_survived, run.events_survived);
+        let config = world.query::<&Config>().single(&world);
+        assert!(run.defense_lost.keys().all(|id| config
+            .events
+            .table
+            .iter()
+            .any(|event| event.id == *id)));
+    }
+
+    #[test]
+    fn run_statistics_are_kept_in_the_save() {
+        let mut world = setup();
+        advance(&mut world, Duration::from_secs(5));
+        crate::input::handle_click(&mut world);
+        let save = crate::save::SaveGame::from_world(&mut world);
+
+        let mut restored = setup();
+        save.apply(&mut restored);
+
+        let (run, lifetime) = statistics(&mut restored);
+        assert_eq!(run.clicks, 1);
+        // The lifetime totals come from their own file instead
+        assert_eq!(lifetime.clicks, 0);
+    }
+
+    #[test]
+    fn purchases_count_every_unit_bought() {
+        let mut world = setup();
+        set_quantity(&mut world, BuyQuantity::Ten);
+        purchase(&mut world, "archer");
+        sell(&mut world, "archer");
+
+        let (run, lifetime) = statistics(&mut world);
+        assert_eq!(run.upgrades_bought, 10);
+        assert_eq!(lifetime.upgrades_bought, 10);
+    }
+
+    #[test]
+    fn a_breached_wave_is_remembered_as_the_fatal_blow() {
+        let mut world = setup();
+        world.query::<&mut Defense>().single_mut(&mut world).0 = 0;
+        let first_wave = world.query::<&Wave>().single(&world).next_at;
+        let now = world.resource::<GameClock>().now();
+        advance(&mut world, first_wave - now);
+
+        world.run_system_once(wave_system).unwrap();
+
+        assert!(*world.query::<&GameState>().single(&world) == GameState::GameOver);
+        let fatal_blow = world.query::<&FatalBlow>().single(&world).0.clone();
+        assert_eq!(
+            fatal_blow,
+            Some(t!("game.game_over.fatal_wave", number = 1).to_string())
+        );
+        assert_eq!(statistics(&mut world).0.castles_fallen, 1);
+    }
+
+    type RunState = (
+        (u64, u64, u64, u64, u64, u64),
+        (Vec<(String, u32)>, usize, usize, String),
+        (Instant, Instant, Instant, Instant, Instant),
+        (u32, Instant),
+        (bool, bool, bool, Option<String>, String),
+        String,
+    );
+
+    /// Everything a run starts from, in a comparable form.
+    fn run_state(world: &mut World) -> RunState {
+        let mut query = world.query::<(
+            (&Gold, &GoldPerSecond, &GoldEarned),
+            (&Defense, &MaxDefense, &DefensePerSecond),
+            (
+                &BoughtUpgrades,
+                &ActiveModifiers,
+                &RepairQueue,
+                &SelectedUpgrade,
+            ),
+            (
+                &LastTick,
+                &LastEventCheck,
+                &LastClick,
+                &LastSave,
+                &RunStarted,
+            ),
+            &Wave,
+            (
+                &GameState,
+                &GameRunning,
+                &OfflineReport,
+                &FatalBlow,
+                &EventMessage,
+            ),
+            &Statistics,
+        )>();
+        let (
+            (gold, gold_ps, gold_earned),
+            (defense, max_defense, defense_ps),
+            (bought_upgrades, modifiers, repairs, selected),
+            (last_tick, last_event_check, last_click, last_save, run_started),
+            wave,
+            (game_state, game_running, offline_report, fatal_blow, event_message),
+            statistics,
+        ) = query.single(world);
+
+        let mut bought: Vec<(String, u32)> = bought_upgrades
+            .0
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(id, count)| (id.clone(), *count))
+            .collect();
+        bought.sort();
+        (
+            (
+                gold.0,
+                gold_ps.0,
+                gold_earned.0,
+                defense.0,
+                max_defense.0,
+                defense_ps.0,
+            ),
+            (
+                bought,
+                modifiers.0.len(),
+                repairs.0.len(),
+                selected.0.clone(),
+            ),
+            (
+                last_tick.0,
+                last_event_check.0,
+                last_click.0,
+                last_save.0,
+                run_started.0,
+            ),
+            (wave.number, wave.next_at),
+            (
+                *game_state == GameState::Playing,
+                game_running.0,
+                offline_report.0.is_some(),
+                fatal_blow.0.clone(),
+                event_message.message.clone(),
+            ),
+            format!("{:?}", statistics.run),
+        )
+    }
+
+    /// Plays a castle until it falls, touching every part of the run on the way.
+    fn play_until_the_castle_falls(world: &mut World) {
+        purchase(world, "trade_hall");
+        purchase(world, "archer");
+        tick(world, 5);
+        crate::input::handle_click(world);
+        world.query::<&mut Defense>().single_mut(world).0 = 10;
+        world.run_system_once(handle_repair_system).unwrap();
+
+        world.query::<&mut Defense>().single_mut(world).0 = 0;
+        let now = world.resource::<GameClock>().now();
+        // A late wave is more than a single archer can hold
+        let mut wave = world.query::<&mut Wave>().single_mut(world);
+        (wave.number, wave.next_at) = (100, now);
+        world.run_system_once(wave_system).unwrap();
+        assert!(*world.query::<&GameState>().single(world) == GameState::GameOver);
+    }
+
+    #[test]
+    fn a_restart_starts_from_the_same_state_as_a_new_game() {
+        let mut world = setup();
+        play_until_the_castle_falls(&mut world);
+        advance(&mut world, Duration::from_secs(30));
+
+        crate::input::reset_game(&mut world);
+
+        let now = world.resource::<GameClock>().now();
+        let mut new_game =
+            crate::setup_world(fixture::config(), GameClock::Manual(now), GameRng::new(0));
+        assert_eq!(run_state(&mut world), run_state(&mut new_game));
+    }
+
+    #[test]
+    fn new_games_start_from_the_configured_values() {
+        let mut world = setup();
+        let config = world.query::<&Config>().single(&world);
+        let (gold, defense) = (config.start.gold, config.start.defense);
+
+        let ((start_gold, _, _, start_defense, _, _), ..) = run_state(&mut world);
+        assert_eq!((start_gold, start_defense), (gold, defense));
+    }
+
+    #[test]
+    fn restarts_reseed_from_the_configured_seed() {
+        let mut world = setup();
+        world.query::<&mut Config>().single_mut(&mut world).rng.seed = Some(42);
+        let mut used = GameRng::new(7);
+        rand::RngCore::next_u64(&mut used);
+        world.insert_resource(used);
+
+        crate::input::reset_game(&mut world);
+
+        let expected = GameRng::new(42).state();
+        let state = world.resource::<GameRng>().state();
+        assert_eq!(
+            (state.seed, state.word_pos),
+            (expected.seed, expected.word_pos)
+        );
+    }
+
+    #[test]
+    fn a_restart_keeps_what_carries_over_between_runs() {
+        let mut world = setup();
+        world.query::<&mut Prestige>().single_mut(&mut world).renown = 7;
+        world
+            .query::<&mut Achievements>()
+            .single_mut(&mut world)
+            .unlocked
+            .insert("first_coin".to_string(), WallTime(0));
+        play_until_the_castle_falls(&mut world);
+
+        crate::input::reset_game(&mut world);
+
+        assert_eq!(world.query::<&Prestige>().single(&world).renown, 7);
+        assert!(world
+            .query::<&Achievements>()
+            .single(&world)
+            .unlocked
+            .contains_key("first_coin"));
+        let (run, lifetime) = statistics(&mut world);
+        assert_eq!((run.clicks, run.castles_fallen), (0, 0));
+        assert_eq!((lifetime.clicks, lifetime.castles_fallen), (1, 1));
+    }
+
+    #[test]
+    fn messages_stay_for_a_fixed_time_whatever_the_cooldown() {
+        let mut world = setup();
+        let now = world.resource::<GameClock>().now();
+        // Keep events out of the way
+        world
+            .query::<&mut LastEventCheck>()
+            .single_mut(&mut world)
+            .0 = now + Duration::from_secs(3600);
+        *world.query::<&mut EventMessage>().single_mut(&mut world) = EventMessage {
+            message: "message".to_string(),
+            timestamp: now,
+        };
+
+        advance(&mut world, MESSAGE_DURATION - Duration::from_millis(100));
+        world.run_system_once(handle_events_system).unwrap();
+        assert_eq!(
+            world.query::<&EventMessage>().single(&world).message,
+            "message"
+        );
+
+        advance(&mut world, Duration::from_millis(100));
+        world.run_system_once(handle_events_system).unwrap();
+        assert!(world
+            .query::<&EventMessage>()
+            .single(&world)
+            .message
+            .is_empty());
+    }
+
+    #[test]
+    fn events_fire_only_after_the_cooldown() {
+        let mut world = setup();
+        let start = world.resource::<GameClock>().now();
+        let cooldown = {
+            let config = world.query::<&Config>().single(&world);
+            Duration::from_secs_f32(config.events.cooldown)
+        };
+
+        advance(&mut world, cooldown - Duration::from_millis(100));
+        world.run_system_once(handle_events_system).unwrap();
+        assert_eq!(world.query::<&LastEventCheck>().single(&world).0, start);
+
+        advance(&mut world, Duration::from_millis(100));
+        world.run_system_once(handle_events_system).unwrap();
+        let now = world.resource::<GameClock>().now();
+        assert_eq!(world.query::<&LastEventCheck>().single(&world).0, now);
     }
 }
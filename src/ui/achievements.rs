@@ -0,0 +1,64 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+use rust_i18n::t;
+
+use crate::{components::Achievements, game::Config};
+
+pub fn render_achievements(
+    frame: &mut Frame,
+    area: Rect,
+    achievements: &Achievements,
+    selected: usize,
+    config: &Config,
+) {
+    let [list_area, footer_area] =
+        Layout::vertical([Constraint::Min(5), Constraint::Length(4)]).areas(area);
+
+    let table = &config.achievements.table;
+    let items: Vec<ListItem> = table
+        .iter()
+        .map(|achievement| {
+            let unlocked = achievements.unlocked.contains_key(&achievement.id);
+            let (icon, style) = if unlocked {
+                (
+                    achievement.icon.as_str(),
+                    Style::default().fg(Color::Yellow),
+                )
+            } else {
+                ("🔒", Style::default().fg(Color::DarkGray))
+            };
+            ListItem::new(vec![
+                Line::from(format!("{} {}", icon, achievement.name())).style(style.bold()),
+                Line::from(format!("   {}", achievement.description())).style(style),
+            ])
+        })
+        .collect();
+
+    let title = t!(
+        "game.achievements.title",
+        unlocked = achievements.unlocked.len(),
+        total = table.len()
+    );
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(Line::from(title).style(Style::default().fg(Color::Yellow).bold()))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .highlight_symbol("▶ ");
+    let mut state = ListState::default().with_selected(Some(selected));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    let footer = Paragraph::new(vec![
+        Line::styled(t!("game.controls.arrows"), Style::default().fg(Color::Gray)),
+        Line::styled(
+            t!("game.achievements.controls.back"),
+            Style::default().fg(Color::Gray),
+        ),
+    ])
+    .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(footer, footer_area);
+}
@@ -1,10 +1,16 @@
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
 };
 use rust_i18n::t;
+use std::time::Duration;
 
-use crate::components::EventMessage;
+use crate::{
+    achievements::AchievementConfig, components::EventMessage, events::EventOdds, game::Config, ui,
+};
+
+/// How long an achievement toast covers the event area.
+pub const TOAST_DURATION: Duration = Duration::from_secs(4);
 
 pub fn render_event(frame: &mut Frame, area: Rect, event: &EventMessage) {
     if !event.message.is_empty() {
@@ -24,3 +30,73 @@ pub fn render_event(frame: &mut Frame, area: Rect, event: &EventMessage) {
         frame.render_widget(event_text, area);
     }
 }
+
+/// Announces a freshly unlocked achievement in place of the event message.
+pub fn render_achievement_toast(frame: &mut Frame, area: Rect, achievement: &AchievementConfig) {
+    let toast_block = Block::default()
+        .title(
+            Line::from(t!("game.achievements.unlocked"))
+                .style(Style::default().fg(Color::Yellow).bold()),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let toast_text = Paragraph::new(
+        Line::from(format!("{} {}", achievement.icon, achievement.name()))
+            .style(Style::default().fg(Color::Yellow).bold()),
+    )
+    .block(toast_block)
+    .alignment(Alignment::Center);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(toast_text, area);
+}
+
+/// Debug overlay with the relative weight of each outcome and the chance it
+/// gives on the next event roll.
+pub fn render_event_odds(frame: &mut Frame, area: Rect, odds: &EventOdds, config: &Config) {
+    let area = ui::create_popup_layout(area, 56, odds.events.len() as u16 + 7);
+
+    let odds_block = Block::default()
+        .title(
+            Line::from(t!("game.events.odds.title"))
+                .style(Style::default().fg(Color::Magenta).bold()),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let outcome = |chance: f32, weight: f32, name: &str| {
+        format!("{:>5.1}% {:>6} {}", chance * 100.0, weight, name)
+    };
+    let mut odds_text = vec![Line::styled(
+        t!("game.events.odds.columns"),
+        Style::default().fg(Color::Gray).bold(),
+    )];
+    odds_text.extend(
+        odds.events
+            .iter()
+            .map(|(event, chance)| Line::from(outcome(*chance, event.weight, &event.message))),
+    );
+    odds_text.push(Line::styled(
+        outcome(
+            odds.nothing,
+            config.events.nothing_weight,
+            &t!("game.events.nothing"),
+        ),
+        Style::default().fg(Color::Gray),
+    ));
+    odds_text.push(Line::from(""));
+    odds_text.push(Line::styled(
+        t!("game.events.odds.note"),
+        Style::default().fg(Color::Gray),
+    ));
+    odds_text.push(Line::styled(
+        t!("game.events.odds.hint"),
+        Style::default().fg(Color::Gray),
+    ));
+
+    let paragraph = Paragraph::new(odds_text).block(odds_block);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
@@ -1,36 +1,66 @@
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Paragraph, Row, Table},
 };
 use rust_i18n::t;
+use std::time::Duration;
 
-use crate::components::{Defense, MaxDefense};
+use crate::{components::RunRecord, ui::format_duration};
+
+pub struct GameOverData<'a> {
+    /// The run that just ended.
+    pub run: Option<&'a RunRecord>,
+    /// The best runs so far, best first.
+    pub best: Vec<&'a RunRecord>,
+    /// Files that could not be written when the castle fell.
+    pub message: &'a str,
+}
+
+pub fn render_game_over(frame: &mut Frame, area: Rect, data: GameOverData) {
+    let [summary_area, best_area] =
+        Layout::vertical([Constraint::Length(15), Constraint::Min(5)]).areas(area);
 
-pub fn render_game_over(
-    frame: &mut Frame,
-    area: Rect,
-    defense: &Defense,
-    max_defense: &MaxDefense,
-) {
     let game_over_block = Block::default()
         .title(Line::from(t!("game.game_over.title")).style(Style::default().fg(Color::Red).bold()))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Red));
 
-    let game_over_text = vec![
+    let mut game_over_text = vec![
         Line::from(""),
         Line::from(vec![Span::styled(
             t!("game.game_over.message"),
             Style::default().fg(Color::Red).bold(),
         )]),
         Line::from(""),
-        Line::from(vec![Span::raw(t!(
-            "game.game_over.score",
-            defense = defense.0,
-            max_defense = max_defense.0
-        ))]),
-        Line::from(""),
-        Line::from(""),
+    ];
+    if let Some(run) = data.run {
+        let cause = run
+            .fatal_blow
+            .clone()
+            .unwrap_or_else(|| t!("game.game_over.fatal_unknown").to_string());
+        game_over_text.extend([
+            Line::from(t!(
+                "game.game_over.survived",
+                time = format_duration(Duration::from_secs_f32(run.survived))
+            )),
+            Line::from(t!("game.game_over.gold_earned", amount = run.gold_earned)),
+            Line::from(t!(
+                "game.game_over.upgrades_bought",
+                count = run.upgrades_bought
+            )),
+            Line::from(t!(
+                "game.game_over.events_survived",
+                count = run.events_survived
+            )),
+            Line::from(t!("game.game_over.fatal_blow", cause = cause)),
+            Line::styled(
+                t!("game.game_over.score", score = run.score),
+                Style::default().fg(Color::Yellow).bold(),
+            ),
+        ]);
+    }
+    game_over_text.extend([
+        Line::styled(data.message.to_string(), Style::default().fg(Color::Red)),
         Line::styled(
             t!("game.game_over.restart"),
             Style::default().fg(Color::Green).bold(),
@@ -39,11 +69,60 @@ pub fn render_game_over(
             t!("game.game_over.exit"),
             Style::default().fg(Color::Yellow).bold(),
         ),
-    ];
+    ]);
 
     let paragraph = Paragraph::new(game_over_text)
         .block(game_over_block)
         .alignment(Alignment::Center);
 
-    frame.render_widget(paragraph, area);
+    frame.render_widget(paragraph, summary_area);
+
+    let rows = data.best.iter().enumerate().map(|(rank, run)| {
+        let style = if data.run.is_some_and(|latest| std::ptr::eq(latest, *run)) {
+            Style::default().fg(Color::Yellow).bold()
+        } else {
+            Style::default()
+        };
+        Row::new([
+            (rank + 1).to_string(),
+            run.ended_at.date(),
+            run.score.to_string(),
+            format_duration(Duration::from_secs_f32(run.survived)),
+            run.gold_earned.to_string(),
+            run.seed.to_string(),
+        ])
+        .style(style)
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(3),
+            Constraint::Length(11),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Min(20),
+        ],
+    )
+    .header(
+        Row::new([
+            "#".to_string(),
+            t!("game.game_over.columns.date").to_string(),
+            t!("game.game_over.columns.score").to_string(),
+            t!("game.game_over.columns.survived").to_string(),
+            t!("game.game_over.columns.gold").to_string(),
+            t!("game.game_over.columns.seed").to_string(),
+        ])
+        .style(Style::default().fg(Color::Gray).bold()),
+    )
+    .block(
+        Block::default()
+            .title(
+                Line::from(t!("game.game_over.best"))
+                    .style(Style::default().fg(Color::Yellow).bold()),
+            )
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    frame.render_widget(table, best_area);
 }
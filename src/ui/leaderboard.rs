@@ -0,0 +1,93 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Row, Table},
+};
+use rust_i18n::t;
+use std::time::Duration;
+
+use crate::{components::Leaderboard, game::Config, score, ui::format_duration};
+
+pub fn render_leaderboard(
+    frame: &mut Frame,
+    area: Rect,
+    leaderboard: &Leaderboard,
+    config: &Config,
+) {
+    let [table_area, footer_area] =
+        Layout::vertical([Constraint::Min(5), Constraint::Length(5)]).areas(area);
+
+    let ranked = score::ranked(leaderboard, config);
+    let block = Block::default()
+        .title(
+            Line::from(t!("game.leaderboard.title"))
+                .style(Style::default().fg(Color::Yellow).bold()),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if ranked.is_empty() {
+        let empty = Paragraph::new(Line::styled(
+            t!("game.leaderboard.empty"),
+            Style::default().fg(Color::DarkGray),
+        ))
+        .block(block)
+        .alignment(Alignment::Center);
+        frame.render_widget(empty, table_area);
+    } else {
+        let rows = ranked.iter().enumerate().map(|(rank, entry)| {
+            Row::new([
+                (rank + 1).to_string(),
+                entry.run.ended_at.date(),
+                entry.run.score.to_string(),
+                format_duration(Duration::from_secs_f32(entry.run.survived)),
+                entry.run.gold_earned.to_string(),
+                entry.run.events_survived.to_string(),
+                entry.run.seed.to_string(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(3),
+                Constraint::Length(11),
+                Constraint::Length(8),
+                Constraint::Length(12),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Min(20),
+            ],
+        )
+        .header(
+            Row::new([
+                "#".to_string(),
+                t!("game.game_over.columns.date").to_string(),
+                t!("game.game_over.columns.score").to_string(),
+                t!("game.game_over.columns.survived").to_string(),
+                t!("game.game_over.columns.gold").to_string(),
+                t!("game.leaderboard.columns.events").to_string(),
+                t!("game.game_over.columns.seed").to_string(),
+            ])
+            .style(Style::default().fg(Color::Gray).bold()),
+        )
+        .block(block);
+        frame.render_widget(table, table_area);
+    }
+
+    let hidden = leaderboard.entries.len() - ranked.len();
+    let footer = Paragraph::new(vec![
+        Line::styled(
+            t!("game.leaderboard.config_hash", hash = config.hash),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Line::styled(
+            t!("game.leaderboard.hidden", count = hidden),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Line::styled(
+            t!("game.leaderboard.controls.back"),
+            Style::default().fg(Color::Gray),
+        ),
+    ])
+    .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(footer, footer_area);
+}
@@ -1,11 +1,20 @@
+pub mod achievements;
 pub mod events;
 pub mod game_over;
+pub mod leaderboard;
+pub mod offline;
+pub mod prestige;
+pub mod statistics;
 pub mod stats;
 pub mod upgrades;
+pub mod waves;
 
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::layout::{Constraint, Direction, Flex, Layout, Rect};
+use rust_i18n::t;
+use std::time::Duration;
 
-pub fn create_layout(area: Rect) -> (Rect, Rect) {
+/// Splits the screen into the event, stats, upgrades and wave areas.
+pub fn create_layout(area: Rect) -> (Rect, Rect, Rect, Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -19,10 +28,40 @@ pub fn create_layout(area: Rect) -> (Rect, Rect) {
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(chunks[1]);
 
-    (chunks[0], main_chunks[0])
+    let side_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(10),    // For the upgrade shop
+            Constraint::Length(10), // For the incoming wave
+        ])
+        .split(main_chunks[1]);
+
+    (chunks[0], main_chunks[0], side_chunks[0], side_chunks[1])
 }
 
 pub fn create_game_over_layout(area: Rect) -> Rect {
     // Use the entire screen for the game over message
     area
 }
+
+pub fn create_popup_layout(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        t!("game.time.hours", hours = hours, minutes = minutes).to_string()
+    } else if minutes > 0 {
+        t!("game.time.minutes", minutes = minutes, seconds = seconds).to_string()
+    } else {
+        t!("game.time.seconds", seconds = seconds).to_string()
+    }
+}
@@ -0,0 +1,72 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use rust_i18n::t;
+
+use crate::{game::OfflineProgress, ui};
+
+pub fn render_offline_report(frame: &mut Frame, area: Rect, progress: &OfflineProgress) {
+    let area = ui::create_popup_layout(area, 48, 12);
+
+    let report_block = Block::default()
+        .title(Line::from(t!("game.offline.title")).style(Style::default().fg(Color::Cyan).bold()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let mut report_text = vec![Line::from(t!(
+        "game.offline.elapsed",
+        time = ui::format_duration(progress.elapsed)
+    ))];
+    if progress.credited < progress.elapsed {
+        report_text.push(Line::styled(
+            t!(
+                "game.offline.credited",
+                time = ui::format_duration(progress.credited)
+            ),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+    report_text.extend([
+        Line::from(vec![
+            Span::styled("💰 ", Style::default().fg(Color::Yellow)),
+            Span::raw(t!(
+                "game.offline.gold",
+                gained = progress.gold_gained,
+                lost = progress.gold_lost
+            )),
+        ]),
+        Line::from(vec![
+            Span::styled("🛡️ ", Style::default().fg(Color::Blue)),
+            Span::raw(t!(
+                "game.offline.defense",
+                gained = progress.defense_gained,
+                lost = progress.defense_lost
+            )),
+        ]),
+        Line::from(vec![
+            Span::styled("⚔️ ", Style::default().fg(Color::Red)),
+            Span::raw(t!("game.offline.events", count = progress.events)),
+        ]),
+        Line::from(vec![
+            Span::styled("🏰 ", Style::default().fg(Color::Red)),
+            Span::raw(t!("game.offline.waves", count = progress.waves)),
+        ]),
+        Line::from(vec![
+            Span::styled("💀 ", Style::default().fg(Color::Red)),
+            Span::raw(t!(
+                "game.offline.units_destroyed",
+                count = progress.units_destroyed
+            )),
+        ]),
+        Line::from(""),
+        Line::styled(t!("game.offline.dismiss"), Style::default().fg(Color::Gray)),
+    ]);
+
+    let paragraph = Paragraph::new(report_text)
+        .block(report_block)
+        .alignment(Alignment::Center);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
@@ -0,0 +1,155 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+};
+use rust_i18n::t;
+
+use crate::{
+    components::Prestige,
+    game::Config,
+    prestige::{PerkBonus, PerkConfig},
+};
+
+pub struct PrestigeData<'a> {
+    pub prestige: &'a Prestige,
+    pub selected: usize,
+    /// Whether abandoning the castle waits for confirmation.
+    pub confirm_abandon: bool,
+    /// Renown abandoning the castle right now would bring.
+    pub gain: u64,
+    pub message: &'a str,
+}
+
+pub fn render_prestige(frame: &mut Frame, area: Rect, data: PrestigeData, config: &Config) {
+    let [summary_area, perks_area, footer_area] = Layout::vertical([
+        Constraint::Length(6),
+        Constraint::Min(5),
+        Constraint::Length(7),
+    ])
+    .areas(area);
+
+    let summary = Paragraph::new(vec![
+        Line::from(vec![
+            Span::styled("⭐ ", Style::default().fg(Color::Yellow)),
+            Span::raw(t!("game.prestige.renown", amount = data.prestige.renown)),
+        ]),
+        Line::from(vec![
+            Span::styled("🏚️ ", Style::default().fg(Color::Gray)),
+            Span::raw(t!(
+                "game.prestige.abandoned",
+                count = data.prestige.castles_abandoned
+            )),
+        ]),
+        Line::from(vec![
+            Span::styled("🚪 ", Style::default().fg(Color::Magenta)),
+            Span::raw(t!("game.prestige.gain", amount = data.gain)),
+        ]),
+    ])
+    .block(
+        Block::default()
+            .title(
+                Line::from(t!("game.prestige.title"))
+                    .style(Style::default().fg(Color::Magenta).bold()),
+            )
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta)),
+    );
+    frame.render_widget(summary, summary_area);
+
+    let items: Vec<ListItem> = config
+        .prestige
+        .perks
+        .iter()
+        .enumerate()
+        .map(|(i, perk)| {
+            let level = data.prestige.level(&perk.id);
+            let style = if i == data.selected {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else if perk.is_maxed(level) {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format_perk(perk, level)).style(style)
+        })
+        .collect();
+    let perks = List::new(items).block(
+        Block::default()
+            .title(
+                Line::from(t!("game.prestige.perks"))
+                    .style(Style::default().fg(Color::Green).bold()),
+            )
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Green)),
+    );
+    frame.render_widget(perks, perks_area);
+
+    let prompt = if data.confirm_abandon {
+        Line::styled(
+            t!("game.prestige.confirm_abandon", amount = data.gain),
+            Style::default().fg(Color::Red).bold(),
+        )
+    } else {
+        Line::styled(data.message.to_string(), Style::default().fg(Color::Cyan))
+    };
+    let footer = Paragraph::new(vec![
+        prompt,
+        Line::from(""),
+        Line::styled(t!("game.controls.arrows"), Style::default().fg(Color::Gray)),
+        Line::styled(
+            t!("game.prestige.controls.buy"),
+            Style::default().fg(Color::Gray),
+        ),
+        Line::styled(
+            t!("game.prestige.controls.abandon"),
+            Style::default().fg(Color::Gray),
+        ),
+        Line::styled(
+            t!("game.prestige.controls.back"),
+            Style::default().fg(Color::Gray),
+        ),
+    ])
+    .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(footer, footer_area);
+}
+
+fn format_perk(perk: &PerkConfig, level: u32) -> String {
+    let total = perk.amount * level as f32;
+    let bonus = match perk.bonus {
+        PerkBonus::GoldPerSecond => t!(
+            "game.prestige.bonus.gold_per_second",
+            amount = format!("{:.0}", total * 100.0)
+        ),
+        PerkBonus::ClickValue => t!("game.prestige.bonus.click_value", amount = total as u64),
+        PerkBonus::EventDamage => t!(
+            "game.prestige.bonus.event_damage",
+            amount = format!("{:.0}", total * 100.0)
+        ),
+    };
+
+    if perk.is_maxed(level) {
+        return format!(
+            "{} {}",
+            perk.icon,
+            t!(
+                "game.prestige.maxed_format",
+                name = perk.name,
+                level = level,
+                bonus = bonus
+            )
+        );
+    }
+    format!(
+        "{} {}",
+        perk.icon,
+        t!(
+            "game.prestige.perk_format",
+            name = perk.name,
+            level = level,
+            bonus = bonus,
+            cost = perk.cost.price(level)
+        )
+    )
+}
@@ -0,0 +1,129 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Row, Table},
+};
+use rust_i18n::t;
+use std::time::Duration;
+
+use crate::{
+    components::{Statistics, Totals},
+    game::Config,
+    ui::format_duration,
+};
+
+pub fn render_statistics(frame: &mut Frame, area: Rect, statistics: &Statistics, config: &Config) {
+    let [table_area, footer_area] =
+        Layout::vertical([Constraint::Min(5), Constraint::Length(3)]).areas(area);
+
+    let mut rows = vec![
+        row(t!("game.statistics.time_played"), statistics, |totals| {
+            format_duration(Duration::from_secs_f64(totals.seconds_played))
+        }),
+        row(t!("game.statistics.clicks"), statistics, |totals| {
+            totals.clicks.to_string()
+        }),
+        row(
+            t!("game.statistics.upgrades_bought"),
+            statistics,
+            |totals| totals.upgrades_bought.to_string(),
+        ),
+        row(
+            t!("game.statistics.gold_from_clicks"),
+            statistics,
+            |totals| totals.gold_from_clicks.to_string(),
+        ),
+        row(
+            t!("game.statistics.gold_from_income"),
+            statistics,
+            |totals| totals.gold_from_income.to_string(),
+        ),
+        row(
+            t!("game.statistics.gold_from_events"),
+            statistics,
+            |totals| totals.gold_from_events.to_string(),
+        ),
+        row(
+            t!("game.statistics.gold_from_waves"),
+            statistics,
+            |totals| totals.gold_from_waves.to_string(),
+        ),
+        row(
+            t!("game.statistics.events_survived"),
+            statistics,
+            |totals| totals.events_survived.to_string(),
+        ),
+        row(t!("game.statistics.waves_repelled"), statistics, |totals| {
+            totals.waves_repelled.to_string()
+        }),
+        row(t!("game.statistics.peak_defense"), statistics, |totals| {
+            totals.peak_defense.to_string()
+        }),
+        row(t!("game.statistics.castles_fallen"), statistics, |totals| {
+            totals.castles_fallen.to_string()
+        }),
+        Row::new([Line::from(t!("game.statistics.defense_lost")).bold()]),
+    ];
+    // Events that have since left the config still count towards the totals
+    for event in &config.events.table {
+        rows.push(row(format!("  {}", event.message), statistics, |totals| {
+            totals
+                .defense_lost
+                .get(&event.id)
+                .copied()
+                .unwrap_or(0)
+                .to_string()
+        }));
+    }
+    rows.push(row(
+        format!("  {}", t!("game.statistics.defense_lost_to_waves")),
+        statistics,
+        |totals| totals.defense_lost_to_waves.to_string(),
+    ));
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(30),
+            Constraint::Length(16),
+            Constraint::Length(16),
+        ],
+    )
+    .header(
+        Row::new([
+            String::new(),
+            t!("game.statistics.run").to_string(),
+            t!("game.statistics.lifetime").to_string(),
+        ])
+        .style(Style::default().fg(Color::Yellow).bold()),
+    )
+    .block(
+        Block::default()
+            .title(
+                Line::from(t!("game.statistics.title"))
+                    .style(Style::default().fg(Color::Cyan).bold()),
+            )
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    frame.render_widget(table, table_area);
+
+    let footer = Paragraph::new(Line::styled(
+        t!("game.statistics.controls.back"),
+        Style::default().fg(Color::Gray),
+    ))
+    .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(footer, footer_area);
+}
+
+/// A row with `value` of this run next to the same value of all time.
+fn row(
+    label: impl Into<String>,
+    statistics: &Statistics,
+    value: impl Fn(&Totals) -> String,
+) -> Row<'static> {
+    Row::new([
+        label.into(),
+        value(&statistics.run),
+        value(&statistics.lifetime),
+    ])
+}
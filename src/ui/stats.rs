@@ -5,9 +5,15 @@ use ratatui::{
 use rust_i18n::t;
 use std::time::Instant;
 
-use crate::components::{Defense, DefensePerSecond, Gold, GoldPerSecond, LastClick};
+use crate::{
+    combat::CombatStats,
+    components::{
+        Defense, DefensePerSecond, Gold, GoldPerSecond, LastClick, MaxDefense, RepairQueue,
+    },
+};
 
 pub struct TimingData<'a> {
+    pub now: Instant,
     pub last_click: &'a LastClick,
     pub click_cooldown: f32,
     pub next_event_cooldown: f32,
@@ -17,7 +23,15 @@ pub struct StatsData<'a> {
     pub gold: &'a Gold,
     pub gold_ps: &'a GoldPerSecond,
     pub defense: &'a Defense,
+    pub max_defense: &'a MaxDefense,
     pub dps: &'a DefensePerSecond,
+    pub repairs: &'a RepairQueue,
+    /// Repair points mended per second.
+    pub repair_speed: u64,
+    /// Multiplier on expected event damage compared to the start of a run.
+    pub threat: f32,
+    pub army: CombatStats,
+    pub seed: u64,
 }
 
 pub fn render_stats(frame: &mut Frame, area: Rect, stats: StatsData, timing: TimingData) {
@@ -26,15 +40,14 @@ pub fn render_stats(frame: &mut Frame, area: Rect, stats: StatsData, timing: Tim
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Yellow));
 
-    let now = Instant::now();
-    let elapsed = now.duration_since(timing.last_click.0).as_secs_f32();
+    let elapsed = timing.now.duration_since(timing.last_click.0).as_secs_f32();
     let cooldown_remaining = if elapsed >= timing.click_cooldown {
         0.0
     } else {
         timing.click_cooldown - elapsed
     };
 
-    let stats_text = vec![
+    let mut stats_text = vec![
         Line::from(vec![
             Span::styled("💰 ", Style::default().fg(Color::Yellow)),
             Span::raw(t!("game.stats.gold", amount = stats.gold.0)),
@@ -45,12 +58,48 @@ pub fn render_stats(frame: &mut Frame, area: Rect, stats: StatsData, timing: Tim
         ]),
         Line::from(vec![
             Span::styled("🛡️ ", Style::default().fg(Color::Blue)),
-            Span::raw(t!("game.stats.defense", amount = stats.defense.0)),
+            Span::raw(t!(
+                "game.stats.defense",
+                amount = stats.defense.0,
+                max = stats.max_defense.0
+            )),
         ]),
+        health_bar(stats.defense.0, stats.max_defense.0),
+    ];
+    if let Some(job) = stats.repairs.0.front() {
+        stats_text.extend([
+            Line::from(vec![
+                Span::styled("🔨 ", Style::default().fg(Color::Cyan)),
+                Span::raw(t!(
+                    "game.stats.repair",
+                    amount = stats.repairs.pending(),
+                    speed = stats.repair_speed
+                )),
+            ]),
+            bar(job.done as f32 / job.points as f32, Color::Cyan),
+        ]);
+    }
+    stats_text.extend([
         Line::from(vec![
             Span::styled("⚔️ ", Style::default().fg(Color::Red)),
             Span::raw(t!("game.stats.defense_per_second", amount = stats.dps.0)),
         ]),
+        Line::from(vec![
+            Span::styled("☠️ ", Style::default().fg(Color::Red)),
+            Span::styled(
+                t!("game.stats.threat", level = format!("{:.1}", stats.threat)),
+                Style::default().fg(threat_color(stats.threat)),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("🏹 ", Style::default().fg(Color::Green)),
+            Span::raw(t!(
+                "game.stats.army",
+                melee = stats.army.melee,
+                ranged = stats.army.ranged,
+                siege = stats.army.siege
+            )),
+        ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("⏳ ", Style::default().fg(Color::Magenta)),
@@ -66,12 +115,39 @@ pub fn render_stats(frame: &mut Frame, area: Rect, stats: StatsData, timing: Tim
                 time = format!("{:.1}", timing.next_event_cooldown)
             )),
         ]),
+        Line::styled(
+            t!("game.stats.seed", seed = stats.seed),
+            Style::default().fg(Color::DarkGray),
+        ),
         Line::from(""),
         Line::styled(t!("game.controls.space"), Style::default().fg(Color::Gray)),
         Line::styled(t!("game.controls.arrows"), Style::default().fg(Color::Gray)),
         Line::styled(t!("game.controls.enter"), Style::default().fg(Color::Gray)),
+        Line::styled(
+            t!("game.controls.quantity"),
+            Style::default().fg(Color::Gray),
+        ),
+        Line::styled(t!("game.controls.sell"), Style::default().fg(Color::Gray)),
+        Line::styled(t!("game.controls.repair"), Style::default().fg(Color::Gray)),
+        Line::styled(t!("game.controls.odds"), Style::default().fg(Color::Gray)),
+        Line::styled(
+            t!("game.controls.prestige"),
+            Style::default().fg(Color::Gray),
+        ),
+        Line::styled(
+            t!("game.controls.achievements"),
+            Style::default().fg(Color::Gray),
+        ),
+        Line::styled(
+            t!("game.controls.statistics"),
+            Style::default().fg(Color::Gray),
+        ),
+        Line::styled(
+            t!("game.controls.leaderboard"),
+            Style::default().fg(Color::Gray),
+        ),
         Line::styled(t!("game.controls.quit"), Style::default().fg(Color::Gray)),
-    ];
+    ]);
 
     let stats = Paragraph::new(stats_text)
         .block(stats_block)
@@ -79,3 +155,37 @@ pub fn render_stats(frame: &mut Frame, area: Rect, stats: StatsData, timing: Tim
 
     frame.render_widget(stats, area);
 }
+
+const BAR_WIDTH: usize = 20;
+
+/// Defense against wall capacity, coloured by how close the walls are to falling.
+fn health_bar(defense: u64, max_defense: u64) -> Line<'static> {
+    let ratio = defense as f32 / max_defense.max(1) as f32;
+    let color = match ratio {
+        ratio if ratio <= 0.25 => Color::Red,
+        ratio if ratio <= 0.5 => Color::Yellow,
+        _ => Color::Green,
+    };
+    bar(ratio, color)
+}
+
+/// A bar of blocks filled to `ratio`.
+fn bar(ratio: f32, color: Color) -> Line<'static> {
+    let filled = ((ratio * BAR_WIDTH as f32).round() as usize).min(BAR_WIDTH);
+    Line::from(vec![
+        Span::raw("   "),
+        Span::styled("█".repeat(filled), Style::default().fg(color)),
+        Span::styled(
+            "░".repeat(BAR_WIDTH - filled),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ])
+}
+
+fn threat_color(threat: f32) -> Color {
+    match threat {
+        threat if threat >= 4.0 => Color::Red,
+        threat if threat >= 2.0 => Color::Yellow,
+        _ => Color::Green,
+    }
+}
@@ -5,76 +5,76 @@ use ratatui::{
 use rust_i18n::t;
 
 use crate::{
-    components::{AvailableUpgrades, BoughtUpgrades, SelectedUpgrade, Upgrades},
-    game::Config,
+    components::{BuyQuantity, SelectedUpgrade, Upgrades},
+    game::{self, Config, UnlockProgress},
 };
 
+pub struct ShopData<'a> {
+    pub upgrades: &'a Upgrades,
+    pub selected: &'a SelectedUpgrade,
+    pub quantity: BuyQuantity,
+    pub gold: u64,
+}
+
 pub fn render_upgrades(
     frame: &mut Frame,
     area: Rect,
-    upgrades: &Upgrades,
-    selected: &SelectedUpgrade,
-    bought_upgrades: &BoughtUpgrades,
+    shop: ShopData,
+    progress: &UnlockProgress,
     config: &Config,
 ) {
+    let ShopData {
+        upgrades,
+        selected,
+        quantity,
+        gold,
+    } = shop;
+
     let upgrades_list: Vec<String> = upgrades
         .0
         .iter()
-        .map(|upgrade| match upgrade {
-            AvailableUpgrades::Catapult => format_upgrade_item(
-                "🗡️",
-                &config.upgrades.catapult.name,
-                upgrade.cost(config, bought_upgrades),
-                bought_upgrades.get_count(upgrade),
-            ),
-            AvailableUpgrades::Archer => format_upgrade_item(
-                "🏹",
-                &config.upgrades.archer.name,
-                upgrade.cost(config, bought_upgrades),
-                bought_upgrades.get_count(upgrade),
-            ),
-            AvailableUpgrades::Warrior => format_upgrade_item(
-                "⚔️",
-                &config.upgrades.warrior.name,
-                upgrade.cost(config, bought_upgrades),
-                bought_upgrades.get_count(upgrade),
-            ),
-            AvailableUpgrades::Officer => format_upgrade_item(
-                "👑",
-                &config.upgrades.officer.name,
-                upgrade.cost(config, bought_upgrades),
-                bought_upgrades.get_count(upgrade),
-            ),
-            AvailableUpgrades::OilReserve => format_upgrade_item(
-                "🔥",
-                &config.upgrades.oil.name,
-                upgrade.cost(config, bought_upgrades),
-                bought_upgrades.get_count(upgrade),
-            ),
-            AvailableUpgrades::TradeHall => {
-                format!(
-                    "🏛️ {}",
+        .filter_map(|id| config.upgrade(id))
+        .map(|upgrade| {
+            if let Some(requirement) = game::missing_requirement(upgrade, progress) {
+                return t!(
+                    "game.upgrades.locked",
+                    name = upgrade.name,
+                    requirement = requirement.describe(progress, config)
+                )
+                .to_string();
+            }
+
+            let level = progress.bought_upgrades.get_count(&upgrade.id);
+            if upgrade.is_maxed(level) {
+                return format!(
+                    "{} {}",
+                    upgrade.icon,
                     t!(
-                        "game.upgrades.trade_hall",
-                        cost = upgrade.cost(config, bought_upgrades),
-                        level = bought_upgrades.get_count(&AvailableUpgrades::TradeHall)
+                        "game.upgrades.maxed_format",
+                        name = upgrade.name,
+                        level = level
                     )
-                )
+                );
             }
-            AvailableUpgrades::MidasHand => {
-                let trade_hall_level = bought_upgrades.get_count(&AvailableUpgrades::TradeHall);
-                if trade_hall_level >= config.midas_hand.required_trade_hall_level {
-                    format!(
-                        "✨ {}",
-                        t!(
-                            "game.upgrades.midas_hand",
-                            cost = upgrade.cost(config, bought_upgrades),
-                            level = bought_upgrades.get_count(&AvailableUpgrades::MidasHand)
-                        )
+
+            let batch = game::plan_batch(upgrade, level, gold, quantity);
+            let name = match quantity {
+                BuyQuantity::One => upgrade.name.clone(),
+                _ => format!("{} x{}", upgrade.name, batch.count),
+            };
+            if upgrade.is_levelled() {
+                format!(
+                    "{} {}",
+                    upgrade.icon,
+                    t!(
+                        "game.upgrades.level_format",
+                        name = name,
+                        cost = batch.cost,
+                        level = level
                     )
-                } else {
-                    t!("game.upgrades.midas_hand_locked").to_string()
-                }
+                )
+            } else {
+                format_upgrade_item(&upgrade.icon, &name, batch.cost, level)
             }
         })
         .collect();
@@ -83,10 +83,19 @@ pub fn render_upgrades(
         .iter()
         .enumerate()
         .map(|(i, upgrade)| {
-            let style = if upgrades.0[i] == selected.0 {
+            let maxed = upgrades
+                .0
+                .get(i)
+                .and_then(|id| config.upgrade(id))
+                .is_some_and(|upgrade| {
+                    upgrade.is_maxed(progress.bought_upgrades.get_count(&upgrade.id))
+                });
+            let style = if upgrades.0.get(i) == Some(&selected.0) {
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD)
+            } else if maxed {
+                Style::default().fg(Color::DarkGray)
             } else {
                 Style::default().fg(Color::White)
             };
@@ -98,8 +107,11 @@ pub fn render_upgrades(
         .block(
             Block::default()
                 .title(
-                    Line::from(t!("game.upgrades.title"))
-                        .style(Style::default().fg(Color::Green).bold()),
+                    Line::from(t!(
+                        "game.upgrades.title",
+                        quantity = quantity_label(quantity)
+                    ))
+                    .style(Style::default().fg(Color::Green).bold()),
                 )
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(Color::Green)),
@@ -125,3 +137,10 @@ fn format_upgrade_item(icon: &str, name: &str, cost: u64, amount: u32) -> String
         )
     )
 }
+
+fn quantity_label(quantity: BuyQuantity) -> String {
+    match quantity.count() {
+        Some(count) => format!("x{count}"),
+        None => t!("game.upgrades.quantity_max").to_string(),
+    }
+}
@@ -0,0 +1,68 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+use rust_i18n::t;
+use std::time::Duration;
+
+use crate::{ui, waves::WaveComposition};
+
+pub struct WaveData<'a> {
+    pub composition: WaveComposition<'a>,
+    pub garrison: u64,
+    pub time_left: Duration,
+}
+
+pub fn render_wave(frame: &mut Frame, area: Rect, wave: WaveData) {
+    let wave_block = Block::default()
+        .title(
+            Line::from(t!("game.waves.title", number = wave.composition.number))
+                .style(Style::default().fg(Color::Red).bold()),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+
+    let attack = wave.composition.strength();
+    let garrison_color = if wave.garrison >= attack {
+        Color::Green
+    } else {
+        Color::Yellow
+    };
+
+    let mut wave_text = vec![Line::from(vec![
+        Span::styled("⏳ ", Style::default().fg(Color::Magenta)),
+        Span::raw(t!(
+            "game.waves.arrives_in",
+            time = ui::format_duration(wave.time_left)
+        )),
+    ])];
+    wave_text.extend(wave.composition.enemies.iter().map(|(enemy, count)| {
+        Line::from(t!(
+            "game.waves.enemy",
+            icon = enemy.icon,
+            name = enemy.name,
+            count = count,
+            strength = enemy.strength * *count as u64
+        ))
+    }));
+    wave_text.extend([
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("⚔️ ", Style::default().fg(Color::Red)),
+            Span::raw(t!("game.waves.attack", amount = attack)),
+        ]),
+        Line::from(vec![
+            Span::styled("🛡️ ", Style::default().fg(Color::Blue)),
+            Span::styled(
+                t!("game.waves.garrison", amount = wave.garrison),
+                Style::default().fg(garrison_color),
+            ),
+        ]),
+    ]);
+
+    let paragraph = Paragraph::new(wave_text)
+        .block(wave_block)
+        .alignment(Alignment::Left);
+
+    frame.render_widget(paragraph, area);
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use crate::{
+    components::BoughtUpgrades,
+    game::{PerLevel, UpgradeConfig},
+};
+
+/// Something owning an upgrade does for the castle.
+#[derive(Debug, Clone)]
+pub enum UpgradeEffect {
+    /// Defense added once on every purchase.
+    Defense(u64),
+    /// Wall capacity per owned unit.
+    MaxDefense(u64),
+    DefensePerSecond(PerLevel),
+    GoldPerSecond(PerLevel),
+    /// Seconds taken off the click cooldown per level.
+    ClickCooldown(f32),
+    /// Repair points per second per owned unit.
+    RepairSpeed(u64),
+}
+
+impl UpgradeEffect {
+    fn max_defense(&self, level: u32) -> u64 {
+        match self {
+            Self::MaxDefense(amount) => amount * level as u64,
+            _ => 0,
+        }
+    }
+
+    fn defense_per_second(&self, level: u32) -> u64 {
+        match self {
+            Self::DefensePerSecond(amount) => amount.value(level),
+            _ => 0,
+        }
+    }
+
+    fn gold_per_second(&self, level: u32) -> u64 {
+        match self {
+            Self::GoldPerSecond(amount) => amount.value(level),
+            _ => 0,
+        }
+    }
+
+    fn repair_speed(&self, level: u32) -> u64 {
+        match self {
+            Self::RepairSpeed(amount) => amount * level as u64,
+            _ => 0,
+        }
+    }
+
+    fn click_cooldown_reduction(&self, level: u32) -> f32 {
+        match self {
+            Self::ClickCooldown(reduction) => reduction * level as f32,
+            _ => 0.0,
+        }
+    }
+}
+
+/// The effects of every upgrade in the catalog, keyed by upgrade id.
+#[derive(Debug, Default)]
+pub struct EffectRegistry(HashMap<String, Vec<UpgradeEffect>>);
+
+impl EffectRegistry {
+    pub fn from_upgrades(upgrades: &[UpgradeConfig]) -> Self {
+        let effects = upgrades
+            .iter()
+            .map(|upgrade| (upgrade.id.clone(), Self::effects_of(upgrade)))
+            .collect();
+        Self(effects)
+    }
+
+    fn effects_of(upgrade: &UpgradeConfig) -> Vec<UpgradeEffect> {
+        let mut effects = Vec::new();
+        if upgrade.defense > 0 {
+            effects.push(UpgradeEffect::Defense(upgrade.defense));
+        }
+        if upgrade.max_defense > 0 {
+            effects.push(UpgradeEffect::MaxDefense(upgrade.max_defense));
+        }
+        if upgrade.defense_per_second.value(1) > 0 {
+            effects.push(UpgradeEffect::DefensePerSecond(
+                upgrade.defense_per_second.clone(),
+            ));
+        }
+        if upgrade.gold_per_second.value(1) > 0 {
+            effects.push(UpgradeEffect::GoldPerSecond(
+                upgrade.gold_per_second.clone(),
+            ));
+        }
+        if upgrade.click_cooldown_reduction > 0.0 {
+            effects.push(UpgradeEffect::ClickCooldown(
+                upgrade.click_cooldown_reduction,
+            ));
+        }
+        if upgrade.repair_speed > 0 {
+            effects.push(UpgradeEffect::RepairSpeed(upgrade.repair_speed));
+        }
+        effects
+    }
+
+    pub fn get(&self, upgrade: &str) -> &[UpgradeEffect] {
+        self.0.get(upgrade).map_or(&[], Vec::as_slice)
+    }
+
+    /// Applies the one-off effects of buying one more `upgrade`.
+    pub fn apply_purchase(&self, upgrade: &str, defense: &mut u64) {
+        for effect in self.get(upgrade) {
+            if let UpgradeEffect::Defense(amount) = effect {
+                *defense += amount;
+            }
+        }
+    }
+
+    /// Takes back the one-off effects of selling one `upgrade`.
+    pub fn apply_sale(&self, upgrade: &str, defense: &mut u64) {
+        for effect in self.get(upgrade) {
+            if let UpgradeEffect::Defense(amount) = effect {
+                *defense = defense.saturating_sub(*amount);
+            }
+        }
+    }
+
+    fn sum<T: std::iter::Sum<T>>(
+        &self,
+        bought_upgrades: &BoughtUpgrades,
+        value: impl Fn(&UpgradeEffect, u32) -> T,
+    ) -> T {
+        self.0
+            .iter()
+            .flat_map(|(upgrade, effects)| {
+                let level = bought_upgrades.get_count(upgrade);
+                effects.iter().map(move |effect| (effect, level))
+            })
+            .map(|(effect, level)| value(effect, level))
+            .sum()
+    }
+
+    pub fn max_defense(&self, bought_upgrades: &BoughtUpgrades) -> u64 {
+        self.sum(bought_upgrades, UpgradeEffect::max_defense)
+    }
+
+    pub fn defense_per_second(&self, bought_upgrades: &BoughtUpgrades) -> u64 {
+        self.sum(bought_upgrades, UpgradeEffect::defense_per_second)
+    }
+
+    pub fn gold_per_second(&self, bought_upgrades: &BoughtUpgrades) -> u64 {
+        self.sum(bought_upgrades, UpgradeEffect::gold_per_second)
+    }
+
+    pub fn repair_speed(&self, bought_upgrades: &BoughtUpgrades) -> u64 {
+        self.sum(bought_upgrades, UpgradeEffect::repair_speed)
+    }
+
+    pub fn click_cooldown_reduction(&self, bought_upgrades: &BoughtUpgrades) -> f32 {
+        self.sum(bought_upgrades, UpgradeEffect::click_cooldown_reduction)
+    }
+}
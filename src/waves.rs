@@ -0,0 +1,258 @@
+use rust_i18n::t;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::{combat, components::BoughtUpgrades, game::Config};
+
+/// The `[waves]` section: scheduled sieges on top of random events.
+#[derive(Debug, Deserialize)]
+pub struct WavesConfig {
+    /// Seconds into the run when the first wave arrives.
+    pub first_wave: f32,
+    /// Seconds between waves.
+    pub interval: f32,
+    /// Gold per point of attack strength for repelling a wave.
+    pub reward: f32,
+    pub enemies: Vec<EnemyConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnemyConfig {
+    pub id: String,
+    pub icon: String,
+    pub name: String,
+    /// Attack strength of a single enemy.
+    pub strength: u64,
+    /// Enemies in the first wave this type appears in.
+    pub count: u32,
+    /// Enemies added with every following wave; fractions accumulate.
+    #[serde(default)]
+    pub per_wave: f32,
+    /// Number of the first wave this type joins.
+    #[serde(default = "first_wave")]
+    pub from_wave: u32,
+}
+
+fn first_wave() -> u32 {
+    1
+}
+
+/// The enemies of one wave.
+pub struct WaveComposition<'a> {
+    pub number: u32,
+    pub enemies: Vec<(&'a EnemyConfig, u32)>,
+}
+
+impl<'a> WaveComposition<'a> {
+    pub fn new(number: u32, config: &'a Config) -> Self {
+        let enemies = config
+            .waves
+            .enemies
+            .iter()
+            .filter(|enemy| number >= enemy.from_wave)
+            .map(|enemy| {
+                let extra = enemy.per_wave * (number - enemy.from_wave) as f32;
+                (enemy, enemy.count + extra as u32)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+
+        Self { number, enemies }
+    }
+
+    pub fn strength(&self) -> u64 {
+        self.enemies
+            .iter()
+            .map(|(enemy, count)| enemy.strength * *count as u64)
+            .sum()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WaveOutcome {
+    pub attack: u64,
+    pub garrison: u64,
+    pub reward: u64,
+    pub defense_lost: u64,
+    pub castle_fell: bool,
+}
+
+/// Combined strength the owned units bring against a wave.
+pub fn garrison_strength(bought_upgrades: &BoughtUpgrades, config: &Config) -> u64 {
+    combat::army_strength(bought_upgrades, config).total()
+}
+
+/// A wave the garrison cannot hold breaks through and hits the walls with
+/// whatever strength is left; a repelled wave pays out its reward.
+pub fn resolve_wave(
+    wave: &WaveComposition,
+    gold: &mut u64,
+    defense: &mut u64,
+    bought_upgrades: &BoughtUpgrades,
+    config: &Config,
+) -> WaveOutcome {
+    let mut outcome = WaveOutcome {
+        attack: wave.strength(),
+        garrison: garrison_strength(bought_upgrades, config),
+        ..Default::default()
+    };
+
+    if outcome.attack <= outcome.garrison {
+        outcome.reward = (outcome.attack as f32 * config.waves.reward) as u64;
+        *gold += outcome.reward;
+        return outcome;
+    }
+
+    let breach = outcome.attack - outcome.garrison;
+    outcome.defense_lost = breach.min(*defense);
+    outcome.castle_fell = *defense < breach;
+    *defense -= outcome.defense_lost;
+    outcome
+}
+
+pub fn describe_wave(number: u32, outcome: &WaveOutcome) -> String {
+    if outcome.castle_fell {
+        t!("game.waves.castle_fall", number = number).to_string()
+    } else if outcome.defense_lost > 0 {
+        t!(
+            "game.waves.breached",
+            number = number,
+            amount = outcome.defense_lost
+        )
+        .to_string()
+    } else {
+        t!(
+            "game.waves.repelled",
+            number = number,
+            amount = outcome.reward
+        )
+        .to_string()
+    }
+}
+
+/// Time between waves.
+pub fn wave_interval(config: &Config) -> Duration {
+    Duration::from_secs_f32(config.waves.interval)
+}
+
+pub fn validate(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if config.waves.first_wave <= 0.0 || config.waves.interval <= 0.0 {
+        return Err("waves.first_wave and waves.interval must be positive".into());
+    }
+    for (index, enemy) in config.waves.enemies.iter().enumerate() {
+        if config.waves.enemies[..index]
+            .iter()
+            .any(|other| other.id == enemy.id)
+        {
+            return Err(format!("duplicate enemy id {}", enemy.id).into());
+        }
+        if enemy.from_wave == 0 {
+            return Err(format!("enemy {} joins wave 0; waves start at 1", enemy.id).into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn counts(number: u32, config: &Config) -> Vec<(&str, u32)> {
+        WaveComposition::new(number, config)
+            .enemies
+            .iter()
+            .map(|(enemy, count)| (enemy.id.as_str(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn enemies_join_from_their_wave_and_grow_every_wave() {
+        let config = fixture::config();
+        let enemy = |id: &str| config.waves.enemies.iter().find(|enemy| enemy.id == id);
+        let (bandits, rams) = (enemy("bandits").unwrap(), enemy("rams").unwrap());
+        let grown =
+            |count: u32, per_wave: f32, waves: u32| count + (per_wave * waves as f32) as u32;
+
+        assert_eq!(counts(1, &config), [("bandits", bandits.count)]);
+        for number in rams.from_wave..rams.from_wave + 4 {
+            assert_eq!(
+                counts(number, &config),
+                [
+                    (
+                        "bandits",
+                        grown(bandits.count, bandits.per_wave, number - 1)
+                    ),
+                    (
+                        "rams",
+                        grown(rams.count, rams.per_wave, number - rams.from_wave)
+                    ),
+                ]
+            );
+        }
+        // Fractions of an enemy accumulate until a whole one joins
+        let rams_at = |number| counts(number, &config)[1].1;
+        assert_eq!(rams_at(rams.from_wave + 1), rams_at(rams.from_wave));
+        assert_eq!(rams_at(rams.from_wave + 2), rams_at(rams.from_wave) + 1);
+    }
+
+    fn garrison(archers: u32) -> BoughtUpgrades {
+        let mut bought_upgrades = BoughtUpgrades::default();
+        for _ in 0..archers {
+            bought_upgrades.increment("archer");
+        }
+        bought_upgrades
+    }
+
+    #[test]
+    fn a_strong_enough_garrison_repels_the_wave_for_a_reward() {
+        let config = fixture::config();
+        let wave = WaveComposition::new(1, &config);
+        let bought_upgrades = garrison(10);
+        let (mut gold, mut defense) = (0, config.start.defense);
+
+        let outcome = resolve_wave(&wave, &mut gold, &mut defense, &bought_upgrades, &config);
+
+        assert!(outcome.garrison >= outcome.attack);
+        assert_eq!(
+            outcome.reward,
+            (wave.strength() as f32 * config.waves.reward) as u64
+        );
+        assert_eq!(gold, outcome.reward);
+        assert_eq!(defense, config.start.defense);
+    }
+
+    #[test]
+    fn a_wave_breaking_through_takes_the_rest_of_its_strength_off_the_defense() {
+        let config = fixture::config();
+        let wave = WaveComposition::new(1, &config);
+        let bought_upgrades = garrison(1);
+        let (mut gold, mut defense) = (0, config.start.defense);
+
+        let outcome = resolve_wave(&wave, &mut gold, &mut defense, &bought_upgrades, &config);
+
+        let breach = wave.strength() - garrison_strength(&bought_upgrades, &config);
+        assert!(breach > 0);
+        assert_eq!(outcome.defense_lost, breach);
+        assert_eq!(defense, config.start.defense - breach);
+        assert_eq!((gold, outcome.castle_fell), (0, false));
+    }
+
+    #[test]
+    fn a_breach_deeper_than_the_defense_takes_the_castle() {
+        let config = fixture::config();
+        let wave = WaveComposition::new(1, &config);
+        let (mut gold, mut defense) = (0, 1);
+
+        let outcome = resolve_wave(
+            &wave,
+            &mut gold,
+            &mut defense,
+            &BoughtUpgrades::default(),
+            &config,
+        );
+
+        assert!(outcome.castle_fell);
+        assert_eq!((outcome.defense_lost, defense), (1, 0));
+    }
+}